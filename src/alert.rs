@@ -0,0 +1,289 @@
+use crate::cgroup;
+use crate::data_collector::ProcessData;
+use crate::process_group;
+use crate::units::{parse_duration, parse_percent, parse_signal, parse_size};
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, Signal, System};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Metric {
+    Cpu,
+    Memory,
+    DiskRead,
+    DiskWrite,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AlertAction {
+    // Only print the alert and highlight it in the chart
+    Notify,
+    // Run a shell command with MONIT_ALERT, MONIT_PID and MONIT_VALUE set
+    Exec(String),
+    // Send a signal to the monitored process (its group or cgroup if it has one)
+    Signal(Signal),
+    // Stop monitoring and exit with the given code
+    Exit(i32),
+}
+
+// Rule in the form `mem>500MB for 10s then exit:3`
+#[derive(Clone, Debug)]
+pub struct AlertRule {
+    pub source: String,
+    pub metric: Metric,
+    pub above: bool,
+    pub threshold: f32,
+    pub duration: Duration,
+    pub action: AlertAction,
+}
+
+impl fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+fn parse_action(value: &str) -> Result<AlertAction, String> {
+    let value = value.trim();
+    let (kind, argument) = value.split_once(':').unwrap_or((value, ""));
+    match kind.trim() {
+        "exec" if !argument.trim().is_empty() => Ok(AlertAction::Exec(argument.trim().to_string())),
+        "signal" | "kill" => parse_signal(argument).map(AlertAction::Signal),
+        "exit" => argument
+            .trim()
+            .parse()
+            .map(AlertAction::Exit)
+            .map_err(|_| format!("invalid exit code '{}'", argument)),
+        _ => Err(format!("unknown alert action '{}'", value)),
+    }
+}
+
+impl FromStr for AlertRule {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let (condition, action) = match source.split_once(" then ") {
+            Some((condition, action)) => (condition, parse_action(action)?),
+            None => (source, AlertAction::Notify),
+        };
+        let (condition, duration) = match condition.split_once(" for ") {
+            Some((condition, duration)) => (condition, parse_duration(duration)?),
+            None => (condition, Duration::ZERO),
+        };
+
+        let (metric, value, above) = if let Some((metric, value)) = condition.split_once('>') {
+            (metric, value, true)
+        } else if let Some((metric, value)) = condition.split_once('<') {
+            (metric, value, false)
+        } else {
+            return Err(format!("expected '>' or '<' in alert '{}'", source));
+        };
+
+        let metric = match metric.trim().to_lowercase().as_str() {
+            "cpu" => Metric::Cpu,
+            "mem" | "memory" | "rss" => Metric::Memory,
+            "read" | "disk_read" => Metric::DiskRead,
+            "write" | "disk_write" => Metric::DiskWrite,
+            other => return Err(format!("unknown alert metric '{}'", other)),
+        };
+
+        let value = value.trim();
+        let threshold = match value.strip_suffix('%') {
+            Some(_) => parse_percent(value).ok(),
+            None => parse_size(value).ok(),
+        }
+        .ok_or_else(|| format!("invalid threshold in alert '{}'", source))?;
+
+        Ok(AlertRule {
+            source: source.trim().to_string(),
+            metric,
+            above,
+            threshold,
+            duration,
+            action,
+        })
+    }
+}
+
+impl AlertRule {
+    pub fn value(&self, process_data: &ProcessData) -> f32 {
+        match self.metric {
+            Metric::Cpu => process_data.cpu_usage,
+            Metric::Memory => process_data.memory_usage,
+            Metric::DiskRead => process_data.disk_read_rate,
+            Metric::DiskWrite => process_data.disk_write_rate,
+        }
+    }
+
+    fn matches(&self, value: f32) -> bool {
+        if self.above {
+            value > self.threshold
+        } else {
+            value < self.threshold
+        }
+    }
+}
+
+// Per-target state of one rule
+pub struct Alert {
+    pub rule: AlertRule,
+    since: Option<Instant>,
+    pub active: bool,
+}
+
+impl Alert {
+    pub fn new(rule: AlertRule) -> Self {
+        Alert {
+            rule,
+            since: None,
+            active: false,
+        }
+    }
+
+    // Returns true only when the rule fires, i.e. the condition has just held for long enough
+    pub fn check(&mut self, process_data: &ProcessData, now: Instant) -> bool {
+        if !self.rule.matches(self.rule.value(process_data)) {
+            self.since = None;
+            self.active = false;
+            return false;
+        }
+
+        let since = *self.since.get_or_insert(now);
+        if !self.active && now.duration_since(since) >= self.rule.duration {
+            self.active = true;
+            return true;
+        }
+        false
+    }
+}
+
+fn run_hook(
+    command: &str,
+    rule: &AlertRule,
+    pid: Pid,
+    cgroup: Option<&Path>,
+    value: f32,
+) -> Result<Child, io::Error> {
+    let mut hook = if cfg!(target_os = "windows") {
+        let mut hook = Command::new("cmd.exe");
+        hook.arg("/c");
+        hook
+    } else {
+        let mut hook = Command::new("sh");
+        hook.arg("-c");
+        hook
+    };
+
+    hook.arg(command)
+        .env("MONIT_ALERT", &rule.source)
+        .env("MONIT_PID", pid.to_string())
+        .env("MONIT_VALUE", format!("{:.2}", value));
+    if let Some(cgroup) = cgroup {
+        hook.env("MONIT_CGROUP", cgroup);
+    }
+    hook.stdout(Stdio::null()).stderr(Stdio::null()).spawn()
+}
+
+// Executes the action of a fired rule, returns the exit code if the tool should stop.
+// For a cgroup target signals go to all of its processes, for a started command
+// (`group`) to its whole process group, since `pid` is only the `sh -c` wrapper
+pub fn run_action(
+    rule: &AlertRule,
+    system: &System,
+    pid: Pid,
+    group: bool,
+    cgroup: Option<&Path>,
+    value: f32,
+) -> Option<i32> {
+    match rule.action {
+        AlertAction::Notify => None,
+        AlertAction::Exec(ref command) => {
+            match run_hook(command, rule, pid, cgroup, value) {
+                // Ждём завершения хука в отдельном потоке, чтобы не оставлять зомби
+                Ok(mut hook) => {
+                    thread::spawn(move || hook.wait());
+                }
+                Err(err) => println!("Failed to run alert hook '{}': {}", command, err),
+            }
+            None
+        }
+        AlertAction::Signal(signal) => {
+            match cgroup {
+                Some(path) => {
+                    if !cgroup::signal(path, system, signal) {
+                        println!("Failed to send {:?} to cgroup {}", signal, path.display());
+                    }
+                }
+                None if group => {
+                    if !process_group::signal(pid, signal) {
+                        println!("Failed to send {:?} to process group {}", signal, pid);
+                    }
+                }
+                None => {
+                    let sent = system
+                        .process(pid)
+                        .and_then(|process| process.kill_with(signal))
+                        .unwrap_or(false);
+                    if !sent {
+                        println!("Failed to send {:?} to PID {}", signal, pid);
+                    }
+                }
+            }
+            None
+        }
+        AlertAction::Exit(code) => Some(code),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_alert_durations() {
+        assert!("cpu>1 for 10s".parse::<AlertRule>().is_ok());
+        assert!("cpu>1 for nan".parse::<AlertRule>().is_err());
+        assert!("cpu>1 for -5s".parse::<AlertRule>().is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_alert_thresholds() {
+        assert!("cpu>80%".parse::<AlertRule>().is_ok());
+        assert!("mem>500MB".parse::<AlertRule>().is_ok());
+        for rule in ["cpu>nan%", "cpu>-5%", "mem>NAN", "mem>infMB", "mem>-1MB"] {
+            assert!(rule.parse::<AlertRule>().is_err(), "{}", rule);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn signals_the_whole_group_of_a_command() {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("sleep 30; true");
+        let mut child = process_group::isolate(&mut command).spawn().unwrap();
+        let pid = Pid::from_u32(child.id());
+        // Даём оболочке запустить sleep
+        thread::sleep(Duration::from_millis(200));
+
+        let rule: AlertRule = "cpu>0 then signal:USR1".parse().unwrap();
+        assert_eq!(run_action(&rule, &System::new(), pid, true, None, 0.), None);
+
+        // sleep тоже получил сигнал, поэтому группа исчезает задолго до 30 секунд
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_secs(5) {
+            if child.try_wait().unwrap().is_some() && !process_group::alive(pid) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        let gone = !process_group::alive(pid);
+        process_group::signal(pid, Signal::Kill);
+        child.wait().unwrap();
+        assert!(gone);
+    }
+}
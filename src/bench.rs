@@ -0,0 +1,329 @@
+use crate::output_capture::OutputCapture;
+use crate::{start_process, Args};
+use monit_tool::data_collector::CpuMode;
+use monit_tool::stats::{mean_variance, welch_t_test};
+use monit_tool::{process_group, Sampler};
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, Signal};
+
+// p-value below which a difference between A and B counts as real
+const SIGNIFICANCE: f64 = 0.05;
+
+// Results of one run of a command
+#[derive(Clone, Copy, Debug)]
+struct Run {
+    // Seconds from the start to the exit of the command
+    wall_time: f64,
+    // None when the value could not be measured, e.g. without rusage
+    // for a command that exits before the first sample. MB
+    peak_memory: Option<f32>,
+    // Mean CPU usage over the run, %
+    mean_cpu: Option<f32>,
+    // Totals of the tree, MB
+    disk_read: Option<f32>,
+    disk_write: Option<f32>,
+    status: ExitStatus,
+}
+
+// Resources the command used, as reported by the kernel when it is reaped
+#[derive(Clone, Copy, Debug)]
+struct Usage {
+    // None when it is not above the peak of monit-tool itself: a child starts
+    // with the peak of its parent, so anything up to it tells nothing. MB
+    peak_memory: Option<f32>,
+    // Seconds spent in user and kernel mode
+    cpu_time: f64,
+    // MB
+    disk_read: f32,
+    disk_write: f32,
+}
+
+// Per-run value that is aggregated and compared between the commands
+struct Measure {
+    label: &'static str,
+    unit: &'static str,
+    value: fn(&Run) -> Option<f64>,
+}
+
+const MEASURES: [Measure; 5] = [
+    Measure {
+        label: "Wall time",
+        unit: "s",
+        value: |run| Some(run.wall_time),
+    },
+    Measure {
+        label: "Peak memory",
+        unit: "MB",
+        value: |run| run.peak_memory.map(f64::from),
+    },
+    Measure {
+        label: "Mean CPU",
+        unit: "%",
+        value: |run| run.mean_cpu.map(f64::from),
+    },
+    Measure {
+        label: "Disk read",
+        unit: "MB",
+        value: |run| run.disk_read.map(f64::from),
+    },
+    Measure {
+        label: "Disk write",
+        unit: "MB",
+        value: |run| run.disk_write.map(f64::from),
+    },
+];
+
+// Runs every command `runs` times and prints the spread of their resource
+// use; with two commands B is also compared against A. Expects one or two commands
+pub fn bench(
+    args: &Args,
+    commands: &[String],
+    runs: usize,
+    running: &AtomicBool,
+) -> Result<(), io::Error> {
+    let mut results: Vec<Vec<Run>> = vec![Vec::new(); commands.len()];
+    // A и B чередуются, чтобы дрейф системы (кэши, частота CPU) влиял на обе одинаково
+    'runs: for number in 1..=runs {
+        for (index, command) in commands.iter().enumerate() {
+            let Some(run) = run_once(args, command, running)? else {
+                println!("Benchmark interrupted");
+                break 'runs;
+            };
+            let mut line = format!(
+                "Run {}/{} [{}]: {:.2} s, peak {} MB, CPU {}%, read {} MB, write {} MB",
+                number,
+                runs,
+                name(index),
+                run.wall_time,
+                format_value(run.peak_memory),
+                format_value(run.mean_cpu),
+                format_value(run.disk_read),
+                format_value(run.disk_write)
+            );
+            if !run.status.success() {
+                line += &format!(" ({})", run.status);
+            }
+            println!("{}", line);
+            results[index].push(run);
+        }
+    }
+
+    for (index, command) in commands.iter().enumerate() {
+        print_runs(index, command, &results[index]);
+    }
+    if let [a, b] = &results[..] {
+        print_comparison(a, b);
+    }
+    Ok(())
+}
+
+fn name(index: usize) -> char {
+    (b'A' + index as u8) as char
+}
+
+fn format_value(value: Option<f32>) -> String {
+    value.map_or(String::from("n/a"), |value| format!("{:.2}", value))
+}
+
+// Starts the command and samples its tree until it exits. None if the
+// benchmark was interrupted, the command is killed then.
+//
+// On unix peak memory, CPU time and disk I/O come from the rusage of the reaped
+// command, which also covers runs shorter than the sampling interval; the
+// samples add what rusage misses (descendants nobody waited for). Values that
+// neither of them measured are left out of the statistics
+fn run_once(args: &Args, command: &str, running: &AtomicBool) -> Result<Option<Run>, io::Error> {
+    // Список процессов читаем до запуска, чтобы не пропустить начало короткой команды
+    let mut sampler = Sampler::new(Pid::from_u32(0));
+    sampler.set_interval(Duration::from_millis(args.sleep));
+    sampler.data_collector.cpu_mode = args.cpu_mode;
+
+    let started = Instant::now();
+    let mut child = start_process(command, &args.workdir)?;
+    // Вывод команды только вычитываем, иначе она встанет на заполненном канале
+    OutputCapture::new(None, &[])?.attach(&mut child);
+    let pid = Pid::from_u32(child.id());
+    // Время работы засекаем в момент выхода, а не на следующем сэмпле
+    let waiter = thread::spawn(move || {
+        wait(child).map(|(status, usage)| (status, usage, started.elapsed().as_secs_f64()))
+    });
+
+    sampler.pid = pid;
+    let mut peak_memory: f32 = 0.;
+    let mut cpu_total = 0.;
+    let mut samples = 0;
+    let mut disk_read: f32 = 0.;
+    let mut disk_write: f32 = 0.;
+    for process_data in sampler.by_ref() {
+        if !running.load(Ordering::SeqCst) {
+            process_group::signal(pid, Signal::Kill);
+            let _ = waiter.join();
+            return Ok(None);
+        }
+        peak_memory = peak_memory.max(process_data.memory_usage);
+        cpu_total += process_data.cpu_usage;
+        samples += 1;
+        disk_read = disk_read.max(process_data.total_read_bytes);
+        disk_write = disk_write.max(process_data.total_written_bytes);
+    }
+
+    let (status, usage, wall_time) = waiter
+        .join()
+        .map_err(|_| io::Error::other("failed to wait for the command"))??;
+    let sampled = |value: f32| (samples > 0).then_some(value);
+    let mut run = Run {
+        wall_time,
+        peak_memory: sampled(peak_memory),
+        mean_cpu: sampled(cpu_total / samples.max(1) as f32),
+        disk_read: sampled(disk_read),
+        disk_write: sampled(disk_write),
+        status,
+    };
+    if let Some(usage) = usage {
+        let max =
+            |value: Option<f32>, usage: f32| Some(value.map_or(usage, |value| value.max(usage)));
+        if let Some(peak) = usage.peak_memory {
+            run.peak_memory = max(run.peak_memory, peak);
+        }
+        // Среднее по времени работы точнее среднего по редким сэмплам
+        let mut cpu = (usage.cpu_time / wall_time.max(f64::EPSILON) * 100.) as f32;
+        if args.cpu_mode == CpuMode::Normalized {
+            cpu /= thread::available_parallelism().map_or(1, |cores| cores.get()) as f32;
+        }
+        run.mean_cpu = Some(cpu);
+        run.disk_read = max(run.disk_read, usage.disk_read);
+        run.disk_write = max(run.disk_write, usage.disk_write);
+    }
+    Ok(Some(run))
+}
+
+// Waits for the command and returns its exit status with the resources used
+// by it and the descendants it waited for
+#[cfg(unix)]
+fn wait(child: Child) -> Result<(ExitStatus, Option<Usage>), io::Error> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let mut status = 0;
+    // SAFETY: rusage is plain data, all zeroes is a valid value
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        // SAFETY: both pointers are valid for the duration of the call
+        let result = unsafe { libc::wait4(child.id() as libc::pid_t, &mut status, 0, &mut usage) };
+        if result >= 0 {
+            break;
+        }
+        let error = io::Error::last_os_error();
+        if error.kind() != io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+
+    // ru_maxrss в килобайтах на Linux и в байтах на macOS
+    let to_mb = |max_rss: libc::c_long| {
+        if cfg!(target_os = "macos") {
+            max_rss as f32 / 1024. / 1024.
+        } else {
+            max_rss as f32 / 1024.
+        }
+    };
+    // SAFETY: as above
+    let mut own: libc::rusage = unsafe { std::mem::zeroed() };
+    // SAFETY: the pointer is valid for the duration of the call
+    unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut own) };
+    let peak_memory = (usage.ru_maxrss > own.ru_maxrss).then(|| to_mb(usage.ru_maxrss));
+    let seconds = |time: libc::timeval| time.tv_sec as f64 + time.tv_usec as f64 / 1_000_000.;
+    // Блоки rusage по 512 байт
+    let blocks = |count: libc::c_long| count as f32 * 512. / 1024. / 1024.;
+    Ok((
+        ExitStatus::from_raw(status),
+        Some(Usage {
+            peak_memory,
+            cpu_time: seconds(usage.ru_utime) + seconds(usage.ru_stime),
+            disk_read: blocks(usage.ru_inblock),
+            disk_write: blocks(usage.ru_oublock),
+        }),
+    ))
+}
+
+#[cfg(not(unix))]
+fn wait(mut child: Child) -> Result<(ExitStatus, Option<Usage>), io::Error> {
+    child.wait().map(|status| (status, None))
+}
+
+// Values of the measure over the runs it was measured in
+fn values(measure: &Measure, runs: &[Run]) -> Vec<f64> {
+    runs.iter().filter_map(measure.value).collect()
+}
+
+fn print_runs(index: usize, command: &str, runs: &[Run]) {
+    println!("\n[{}] {}", name(index), command);
+    let failed = runs.iter().filter(|run| !run.status.success()).count();
+    if failed > 0 {
+        println!("Runs: {} ({} failed)", runs.len(), failed);
+    } else {
+        println!("Runs: {}", runs.len());
+    }
+    if runs.is_empty() {
+        return;
+    }
+    for measure in &MEASURES {
+        let values = values(measure, runs);
+        if values.is_empty() {
+            println!("{}: no measured runs", measure.label);
+            continue;
+        }
+        let (mean, variance) = mean_variance(&values);
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let mut line = format!(
+            "{}: {:.2} {} ± {:.2} (Min: {:.2}, Max: {:.2}, Variance: {:.4})",
+            measure.label,
+            mean,
+            measure.unit,
+            variance.sqrt(),
+            min,
+            max,
+            variance
+        );
+        if values.len() < runs.len() {
+            line += &format!(", measured in {} of {} runs", values.len(), runs.len());
+        }
+        println!("{}", line);
+    }
+}
+
+fn print_comparison(a: &[Run], b: &[Run]) {
+    println!("\nB vs A (Welch's t-test, p < {}):", SIGNIFICANCE);
+    for measure in &MEASURES {
+        let values_a = values(measure, a);
+        let values_b = values(measure, b);
+        if values_a.is_empty() || values_b.is_empty() {
+            println!("{}: not measured for both commands", measure.label);
+            continue;
+        }
+        let (mean_a, _) = mean_variance(&values_a);
+        let (mean_b, _) = mean_variance(&values_b);
+        let change = if mean_a != 0. {
+            format!("{:+.1}%", (mean_b - mean_a) / mean_a * 100.)
+        } else {
+            String::from("n/a")
+        };
+        let verdict = match welch_t_test(&values_a, &values_b) {
+            None => String::from("not enough runs to tell"),
+            Some(p) if p < SIGNIFICANCE => format!(
+                "p = {:.3}, B is {}",
+                p,
+                if mean_b < mean_a { "lower" } else { "higher" }
+            ),
+            Some(p) => format!("p = {:.3}, no significant difference", p),
+        };
+        println!(
+            "{}: {:.2} {} -> {:.2} {} ({}), {}",
+            measure.label, mean_a, measure.unit, mean_b, measure.unit, change, verdict
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use crate::summary::Summary;
+use std::time::Duration;
+
+// Exit code used when at least one resource budget is exceeded. The README
+// examples use 3 for `exit:` alerts, so CI can tell the two apart
+pub const BUDGET_EXIT_CODE: i32 = 4;
+
+// Resource limits checked against the final statistics (for CI)
+pub struct Budgets {
+    pub max_memory: Option<f32>,
+    pub max_avg_cpu: Option<f32>,
+    pub max_runtime: Option<Duration>,
+    pub max_disk_write: Option<f32>,
+}
+
+impl Budgets {
+    pub fn runtime_exceeded(&self, elapsed: Duration) -> bool {
+        self.max_runtime
+            .is_some_and(|max_runtime| elapsed > max_runtime)
+    }
+
+    // Returns a human-readable line for every breached budget
+    pub fn check(&self, summaries: &[Summary], elapsed: Duration) -> Vec<String> {
+        let mut breaches = Vec::new();
+
+        let per_target = self.max_memory.is_some()
+            || self.max_avg_cpu.is_some()
+            || self.max_disk_write.is_some();
+        for summary in summaries {
+            // Без замеров бюджет проверить нельзя, а молча пропускать цель в CI нельзя
+            if per_target && summary.samples == 0 {
+                breaches.push(format!(
+                    "[{}] No samples collected, budgets cannot be checked",
+                    summary.label
+                ));
+                continue;
+            }
+            if let Some(max_memory) = self.max_memory {
+                if summary.max_memory_usage > max_memory {
+                    breaches.push(format!(
+                        "[{}] Max Memory Usage {:.2} MB > {:.2} MB",
+                        summary.label, summary.max_memory_usage, max_memory
+                    ));
+                }
+            }
+            if let Some(max_avg_cpu) = self.max_avg_cpu {
+                if summary.avg_cpu_usage > max_avg_cpu {
+                    breaches.push(format!(
+                        "[{}] Average CPU Usage {:.2}% > {:.2}%",
+                        summary.label, summary.avg_cpu_usage, max_avg_cpu
+                    ));
+                }
+            }
+            if let Some(max_disk_write) = self.max_disk_write {
+                if summary.total_disk_write > max_disk_write {
+                    breaches.push(format!(
+                        "[{}] Total Disk Write {:.2} MB > {:.2} MB",
+                        summary.label, summary.total_disk_write, max_disk_write
+                    ));
+                }
+            }
+        }
+
+        if let Some(max_runtime) = self.max_runtime {
+            if elapsed > max_runtime {
+                breaches.push(format!(
+                    "Total runtime {:.2}s > {:.2}s",
+                    elapsed.as_secs_f32(),
+                    max_runtime.as_secs_f32()
+                ));
+            }
+        }
+
+        breaches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::target::Target;
+    use sysinfo::Pid;
+
+    fn budgets(max_memory: Option<f32>) -> Budgets {
+        Budgets {
+            max_memory,
+            max_avg_cpu: None,
+            max_runtime: None,
+            max_disk_write: None,
+        }
+    }
+
+    #[test]
+    fn target_without_samples_breaches_budgets() {
+        let summary = Summary::from_target(&Target::new(Pid::from_u32(1), None, None));
+        assert_eq!(summary.samples, 0);
+        let summaries = [summary];
+
+        let breaches = budgets(Some(100.)).check(&summaries, Duration::ZERO);
+        assert_eq!(
+            breaches,
+            ["[PID 1] No samples collected, budgets cannot be checked"]
+        );
+        // Без бюджетов отсутствие замеров не ошибка
+        assert!(budgets(None).check(&summaries, Duration::ZERO).is_empty());
+    }
+}
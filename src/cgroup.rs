@@ -0,0 +1,118 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use sysinfo::{Pid, Signal, System};
+
+// Mount point of the cgroup v2 hierarchy
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+// Totals of a cgroup v2 directory, `None` if the controller file is missing
+pub struct CgroupStats {
+    // Processes of the cgroup and all nested cgroups
+    pub pids: Vec<Pid>,
+    pub memory_bytes: Option<u64>,
+    pub cpu_usage_usec: Option<u64>,
+    // (read, written) bytes summed over all devices
+    pub io_bytes: Option<(u64, u64)>,
+}
+
+// Paths under the cgroup root are used as they are. Other paths are taken from
+// the root, both relative ones (`system.slice/nginx.service`) and the absolute
+// ones systemd and `/proc/<pid>/cgroup` print (`/system.slice/nginx.service`)
+pub fn resolve(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.starts_with(CGROUP_ROOT) {
+        path.to_path_buf()
+    } else {
+        Path::new(CGROUP_ROOT).join(path.strip_prefix("/").unwrap_or(path))
+    }
+}
+
+pub fn read(path: &Path) -> io::Result<CgroupStats> {
+    let mut pids = Vec::new();
+    collect_pids(path, &mut pids)?;
+
+    let memory_bytes =
+        read_file(path, "memory.current").and_then(|value| value.trim().parse().ok());
+    let cpu_usage_usec = read_file(path, "cpu.stat").and_then(|stat| {
+        stat.lines()
+            .find_map(|line| line.strip_prefix("usage_usec "))
+            .and_then(|value| value.trim().parse().ok())
+    });
+    let io_bytes = read_file(path, "io.stat").map(|stat| {
+        // Строки вида `8:0 rbytes=1 wbytes=2 rios=3 wios=4 dbytes=0 dios=0`
+        let mut total = (0, 0);
+        for field in stat.split_whitespace() {
+            if let Some(value) = field.strip_prefix("rbytes=") {
+                total.0 += value.parse::<u64>().unwrap_or(0);
+            } else if let Some(value) = field.strip_prefix("wbytes=") {
+                total.1 += value.parse::<u64>().unwrap_or(0);
+            }
+        }
+        total
+    });
+
+    Ok(CgroupStats {
+        pids,
+        memory_bytes,
+        cpu_usage_usec,
+        io_bytes,
+    })
+}
+
+// Sends the signal to every process of the cgroup and its nested cgroups,
+// false if none of them got it. SIGKILL goes through `cgroup.kill` where the
+// kernel has it, which also catches processes forked in the meantime
+pub fn signal(path: &Path, system: &System, signal: Signal) -> bool {
+    if signal == Signal::Kill && fs::write(path.join("cgroup.kill"), "1").is_ok() {
+        return true;
+    }
+    let mut pids = Vec::new();
+    if collect_pids(path, &mut pids).is_err() {
+        return false;
+    }
+    let mut sent = false;
+    for pid in pids {
+        sent |= system
+            .process(pid)
+            .and_then(|process| process.kill_with(signal))
+            .unwrap_or(false);
+    }
+    sent
+}
+
+fn read_file(path: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(path.join(name)).ok()
+}
+
+fn collect_pids(path: &Path, pids: &mut Vec<Pid>) -> io::Result<()> {
+    let procs = fs::read_to_string(path.join("cgroup.procs"))?;
+    pids.extend(
+        procs
+            .lines()
+            .filter_map(|line| line.trim().parse::<u32>().ok())
+            .map(Pid::from_u32),
+    );
+    for entry in fs::read_dir(path)?.flatten() {
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            // Вложенная группа могла исчезнуть между чтениями
+            let _ = collect_pids(&entry.path(), pids);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_paths_from_the_cgroup_root() {
+        let nginx = Path::new("/sys/fs/cgroup/system.slice/nginx.service");
+        assert_eq!(resolve("system.slice/nginx.service"), nginx);
+        assert_eq!(resolve("/system.slice/nginx.service"), nginx);
+        assert_eq!(resolve("/sys/fs/cgroup/system.slice/nginx.service"), nginx);
+        assert_eq!(resolve("/"), Path::new(CGROUP_ROOT));
+        assert_eq!(resolve("/sys/fs/cgroup"), Path::new(CGROUP_ROOT));
+    }
+}
@@ -1,13 +1,71 @@
-use crate::{BLUE, ORANGE, PRINT_LEN, PURPLE};
+use crate::format_duration;
+use chrono::{DateTime, Local, TimeZone};
+use clap::ValueEnum;
+use monit_tool::colors::YELLOW;
 use rgb::RGB8;
 use textplots::{Chart, ColorPlot, LabelBuilder, LabelFormat, Shape};
 
+// Smallest height of one sub-chart of the stacked layout, in dots
+const MIN_SUB_CHART_HEIGHT: u32 = 8;
+
+// Steps between the time ticks in seconds, the smallest one that keeps the labels apart is used
+const TICK_STEPS: [i64; 14] = [
+    1, 2, 5, 10, 15, 30, 60, 120, 300, 600, 900, 1800, 3600, 7200,
+];
+// Columns kept free for one tick label
+const TICK_COLUMNS: usize = 12;
+
+// How the time ticks under the charts are labeled
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum TimeAxis {
+    // Wall-clock time of the samples
+    Wall,
+    // Time since the first sample
+    Elapsed,
+}
+
+impl TimeAxis {
+    pub fn format(self, time: DateTime<Local>, first_sample: DateTime<Local>) -> String {
+        match self {
+            TimeAxis::Wall => time.format("%H:%M:%S").to_string(),
+            TimeAxis::Elapsed => format!(
+                "+{}",
+                format_duration((time - first_sample).to_std().unwrap_or_default())
+            ),
+        }
+    }
+}
+
+// How the series of the main chart share the Y-axis
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Layout {
+    // All series on one axis up to the memory peak
+    Overlay,
+    // One sub-chart with its own axis per series, each as high as a chart
+    Stacked,
+    // All series on one chart, each scaled to its own maximum
+    Normalized,
+}
+
+// One line of the main chart
+pub struct ChartSeries {
+    pub name: &'static str,
+    pub data: Vec<(f32, f32)>,
+    pub color: RGB8,
+    pub unit: &'static str,
+}
+
+// Visible part of a line
+struct Series<'a> {
+    name: &'static str,
+    data: &'a [(f32, f32)],
+    color: RGB8,
+    unit: &'static str,
+}
+
 pub struct ChartManager {
-    // Data for CPU, memory, disk write, and disk read
-    pub cpu_data: Vec<(f32, f32)>,
-    pub memory_data: Vec<(f32, f32)>,
-    pub disk_write_data: Vec<(f32, f32)>,
-    pub disk_read_data: Vec<(f32, f32)>,
+    // Enabled lines of the main chart, in drawing order
+    pub series: Vec<ChartSeries>,
 
     // Maximum value for the Y-axis
     pub max: f32,
@@ -19,83 +77,149 @@ pub struct ChartManager {
     pub cpu_usage: f32,
     pub memory_usage: f32,
 
-    // Color for memory data
-    pub memory_color: RGB8,
+    // Number of latest samples shown on the X-axis
+    pub window: usize,
+    // Seconds shown on the X-axis instead, if set
+    pub span: Option<f32>,
+
+    // Labels of the time ticks and the samples they are counted from
+    pub time_axis: TimeAxis,
+    pub first_sample: DateTime<Local>,
+    pub last_sample: DateTime<Local>,
+
+    // Size of the charts in braille dots, 2 per terminal column and 4 per row
+    pub width: u32,
+    pub height: u32,
 
-    // Flags indicating whether disk write and read data should be displayed
-    pub disk_write: bool,
-    pub disk_read: bool,
+    // How the series share the Y-axis and whether values are log-scaled
+    pub layout: Layout,
+    pub log_scale: bool,
+
+    // X positions of output pattern marks, drawn as vertical lines
+    pub marks: Vec<f32>,
+
+    // Memory lines of individual child processes and their label
+    pub children_data: Vec<(Vec<(f32, f32)>, RGB8)>,
+    pub children_label: String,
+
+    // Detail series from /proc selected with `--chart` and their label
+    pub details_data: Vec<(Vec<(f32, f32)>, RGB8)>,
+    pub details_label: String,
 }
 
 impl ChartManager {
     pub fn new() -> Self {
         ChartManager {
-            cpu_data: vec![],
-            memory_data: vec![],
-            disk_write_data: vec![],
-            disk_read_data: vec![],
+            series: vec![],
             max: 0.0,
             x_label: String::new(),
             cpu_usage: 0.0,
             memory_usage: 0.0,
-            memory_color: RGB8::new(0, 0, 0), // Замените этот цвет на начальное значение
-            disk_write: false,
-            disk_read: false,
+            window: usize::MAX,
+            span: None,
+            time_axis: TimeAxis::Wall,
+            first_sample: Local::now(),
+            last_sample: Local::now(),
+            width: 280,
+            height: 40,
+            layout: Layout::Overlay,
+            log_scale: false,
+            marks: vec![],
+            children_data: vec![],
+            children_label: String::new(),
+            details_data: vec![],
+            details_label: String::new(),
         }
     }
 
-    pub fn set_cpu_data(&mut self, data: &[(f32, f32); PRINT_LEN]) -> &mut ChartManager {
-        self.cpu_data = Vec::from(data);
+    pub fn set_series(&mut self, series: Vec<ChartSeries>) -> &mut ChartManager {
+        self.series = series;
         self
     }
 
-    pub fn set_memory_data(&mut self, data: &[(f32, f32); PRINT_LEN]) -> &mut ChartManager {
-        self.memory_data = Vec::from(data);
+    pub fn set_max(&mut self, max: f32) -> &mut ChartManager {
+        self.max = max;
         self
     }
 
-    pub fn set_disk_write_data(&mut self, data: &[(f32, f32); PRINT_LEN]) -> &mut ChartManager {
-        self.disk_write_data = Vec::from(data);
+    pub fn set_x_label(&mut self, label: String) -> &mut ChartManager {
+        self.x_label = label;
         self
     }
 
-    pub fn set_disk_read_data(&mut self, data: &[(f32, f32); PRINT_LEN]) -> &mut ChartManager {
-        self.disk_read_data = Vec::from(data);
+    pub fn set_cpu_usage(&mut self, usage: f32) -> &mut ChartManager {
+        self.cpu_usage = usage;
         self
     }
 
-    pub fn set_max(&mut self, max: f32) -> &mut ChartManager {
-        self.max = max;
+    pub fn set_memory_usage(&mut self, usage: f32) -> &mut ChartManager {
+        self.memory_usage = usage;
         self
     }
 
-    pub fn set_x_label(&mut self, label: String) -> &mut ChartManager {
-        self.x_label = label;
+    pub fn set_window(&mut self, window: usize) -> &mut ChartManager {
+        self.window = window;
         self
     }
 
-    pub fn set_cpu_usage(&mut self, usage: f32) -> &mut ChartManager {
-        self.cpu_usage = usage;
+    pub fn set_span(&mut self, span: Option<f32>) -> &mut ChartManager {
+        self.span = span;
         self
     }
 
-    pub fn set_memory_usage(&mut self, usage: f32) -> &mut ChartManager {
-        self.memory_usage = usage;
+    pub fn set_time_axis(&mut self, time_axis: TimeAxis) -> &mut ChartManager {
+        self.time_axis = time_axis;
+        self
+    }
+
+    pub fn set_sample_times(
+        &mut self,
+        first_sample: DateTime<Local>,
+        last_sample: DateTime<Local>,
+    ) -> &mut ChartManager {
+        self.first_sample = first_sample;
+        self.last_sample = last_sample;
+        self
+    }
+
+    pub fn set_size(&mut self, width: u32, height: u32) -> &mut ChartManager {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn set_layout(&mut self, layout: Layout) -> &mut ChartManager {
+        self.layout = layout;
+        self
+    }
+
+    pub fn set_log_scale(&mut self, log_scale: bool) -> &mut ChartManager {
+        self.log_scale = log_scale;
+        self
+    }
+
+    pub fn set_marks(&mut self, marks: Vec<f32>) -> &mut ChartManager {
+        self.marks = marks;
         self
     }
 
-    pub fn set_memory_color(&mut self, color: RGB8) -> &mut ChartManager {
-        self.memory_color = color;
+    pub fn set_children_data(&mut self, data: Vec<(Vec<(f32, f32)>, RGB8)>) -> &mut ChartManager {
+        self.children_data = data;
         self
     }
 
-    pub fn set_disk_write(&mut self, write: bool) -> &mut ChartManager {
-        self.disk_write = write;
+    pub fn set_children_label(&mut self, label: String) -> &mut ChartManager {
+        self.children_label = label;
         self
     }
 
-    pub fn set_disk_read(&mut self, read: bool) -> &mut ChartManager {
-        self.disk_read = read;
+    pub fn set_details_data(&mut self, data: Vec<(Vec<(f32, f32)>, RGB8)>) -> &mut ChartManager {
+        self.details_data = data;
+        self
+    }
+
+    pub fn set_details_label(&mut self, label: String) -> &mut ChartManager {
+        self.details_label = label;
         self
     }
 }
@@ -103,48 +227,308 @@ impl ChartManager {
 impl ChartManager {
     // Function to draw the chart based on the configured data
     pub fn draw_chart(&self) {
+        // Все истории одной цели одинаковой длины, окно считаем по первой
+        let (start, span) = self
+            .series
+            .first()
+            .map_or((0, 1.), |line| self.visible(&line.data));
+
+        let series: Vec<Series> = self
+            .series
+            .iter()
+            .map(|line| Series {
+                name: line.name,
+                data: &line.data[start.min(line.data.len())..],
+                color: line.color,
+                unit: line.unit,
+            })
+            .collect();
+
+        match self.layout {
+            Layout::Overlay => self.draw_overlay(&series, span),
+            Layout::Stacked => {
+                // Каждый подграфик высотой в целый график, но не меньше читаемой
+                let height = self.height.max(MIN_SUB_CHART_HEIGHT);
+                for (index, line) in series.iter().enumerate() {
+                    // Общая подпись с тиком и процессом - под последним графиком
+                    let label = if index + 1 == series.len() {
+                        format!("{}: {}", line.name, self.x_label)
+                    } else {
+                        format!("{}: {:.2} {}", line.name, last_value(line.data), line.unit)
+                    };
+                    self.draw_stacked(line, span, height, label);
+                }
+            }
+            Layout::Normalized => self.draw_normalized(&series, span),
+        }
+    }
+
+    // First visible point of a series and the seconds the X-axis covers: the
+    // latest `window` samples, cut to `span` seconds if it is set
+    fn visible(&self, data: &[(f32, f32)]) -> (usize, f32) {
+        let mut start = data.len() - self.window.min(data.len());
+        if let Some(span) = self.span {
+            while start + 1 < data.len() && data[start].0 > span {
+                start += 1;
+            }
+            return (start, span.max(1.));
+        }
+        (start, data.get(start).map_or(0., |&(x, _)| x).max(1.))
+    }
+
+    // All series against the memory peak, as the chart has always been drawn
+    fn draw_overlay(&self, series: &[Series], span: f32) {
+        let max = self.scale(self.max);
+        let cpu_usage = self.cpu_usage;
+        let memory_usage = self.memory_usage;
+        let y_label = move |val: f32| {
+            if val == 0. {
+                return format!("{:.2}%", cpu_usage);
+            }
+            format!("{:.2} MB", memory_usage)
+        };
+        let lines: Vec<(Vec<(f32, f32)>, RGB8)> = series
+            .iter()
+            .map(|series| (self.scale_points(series.data, 1.), series.color))
+            .collect();
+        self.plot(
+            &lines,
+            span,
+            self.height,
+            max,
+            self.x_label.clone(),
+            y_label,
+        );
+    }
+
+    // One series with its own axis, labeled in its unit
+    fn draw_stacked(&self, series: &Series, span: f32, height: u32, label: String) {
+        let max = self.scale(
+            series
+                .data
+                .iter()
+                .fold(1., |max, &(_, value)| value.max(max)),
+        );
+        let log_scale = self.log_scale;
+        let unit = series.unit;
+        let y_label = move |val: f32| format!("{:.2} {}", unscale(val, log_scale), unit);
+        let lines = vec![(self.scale_points(series.data, 1.), series.color)];
+        self.plot(&lines, span, height, max, label, y_label);
+    }
+
+    // Every series scaled to its own maximum in the window, the maxima are listed in the label
+    fn draw_normalized(&self, series: &[Series], span: f32) {
+        let mut scales = Vec::new();
+        let mut lines = Vec::new();
+        for series in series {
+            let max = series
+                .data
+                .iter()
+                .fold(0., |max, &(_, value)| value.max(max));
+            scales.push(format!("{} 0-{:.2} {}", series.name, max, series.unit));
+            let factor = if max > 0. { 1. / self.scale(max) } else { 0. };
+            lines.push((self.scale_points(series.data, factor), series.color));
+        }
+        let label = format!("{} | scale: {}", self.x_label, scales.join(", "));
+        let y_label = |val: f32| format!("{:.0}% of max", val * 100.);
+        self.plot(&lines, span, self.height, 1., label, y_label);
+    }
+
+    // Draws the lines and the output marks on one chart
+    fn plot(
+        &self,
+        lines: &[(Vec<(f32, f32)>, RGB8)],
+        span: f32,
+        height: u32,
+        max: f32,
+        x_label: String,
+        y_label: impl Fn(f32) -> String + 'static,
+    ) {
         // Create a new chart with specified settings
-        let mut chart =
-            Chart::new_with_y_range(280, 40, -1.5, self.cpu_data.len() as f32, 0., self.max);
+        let mut chart = Chart::new_with_y_range(self.width, height, 0., span, 0., max.max(0.01));
         chart.axis();
         chart.figures();
 
-        // Define shapes for CPU, memory, disk write, and disk read data
-        let cpu_shape = Shape::Lines(&*self.cpu_data);
-        let memory_shape = Shape::Lines(&*self.memory_data);
-        let disk_write_shape = Shape::Lines(&*self.disk_write_data);
-        let disk_read_shape = Shape::Lines(&*self.disk_read_data);
+        let mark_lines: Vec<[(f32, f32); 2]> = self
+            .marks
+            .iter()
+            .filter(|&&x| x <= span)
+            .map(|&x| [(x, 0.), (x, max)])
+            .collect();
+        let mark_shapes: Vec<Shape> = mark_lines.iter().map(|line| Shape::Lines(line)).collect();
+        let shapes: Vec<(Shape, RGB8)> = lines
+            .iter()
+            .map(|(data, color)| (Shape::Lines(data), *color))
+            .collect();
 
         // Create and configure the plot with different line colors
-        let mut dots = chart.linecolorplot(&cpu_shape, ORANGE);
-        dots = dots.linecolorplot(&memory_shape, self.memory_color);
-        if self.disk_write {
-            dots = dots.linecolorplot(&disk_write_shape, PURPLE);
+        let mut dots = &mut chart;
+        for mark_shape in &mark_shapes {
+            dots = dots.linecolorplot(mark_shape, YELLOW);
         }
-        if self.disk_read {
-            dots = dots.linecolorplot(&disk_read_shape, BLUE);
+        for (shape, color) in &shapes {
+            dots = dots.linecolorplot(shape, *color);
         }
 
         // Configure the x-axis label using a closure
-        let xlabel = self.x_label.clone();
         dots = dots.x_label_format(LabelFormat::Custom(Box::new(move |val| {
             if val > 0. {
                 return String::new();
             }
-            xlabel.clone()
+            x_label.clone()
         })));
 
         // Configure the y-axis label using a closure
-        let cpu_usage = self.cpu_usage.clone();
-        let memory_usage = self.memory_usage.clone();
-        dots = dots.y_label_format(LabelFormat::Custom(Box::new(move |val| {
-            if val == 0. {
-                return format!("{:.2}%", cpu_usage);
+        dots = dots.y_label_format(LabelFormat::Custom(Box::new(y_label)));
+
+        // Display the chart
+        dots.display();
+        println!("{}", self.time_ticks(span));
+    }
+
+    // Line of time labels under a chart, each one starts at the column of its time
+    fn time_ticks(&self, span: f32) -> String {
+        let columns = (self.width / 2) as usize;
+        let seconds_per_column = span / columns.max(1) as f32;
+        let step = TICK_STEPS
+            .into_iter()
+            .find(|&step| step as f32 >= seconds_per_column * TICK_COLUMNS as f32)
+            .unwrap_or(TICK_STEPS[TICK_STEPS.len() - 1]);
+
+        // Время последнего замера в секундах на выбранной шкале
+        let now = match self.time_axis {
+            TimeAxis::Wall => self.last_sample.timestamp_millis(),
+            TimeAxis::Elapsed => (self.last_sample - self.first_sample).num_milliseconds(),
+        } as f64
+            / 1000.;
+        let mut line = vec![' '; columns + TICK_COLUMNS];
+        let mut tick = (now / step as f64).floor() as i64 * step;
+        loop {
+            let age = (now - tick as f64) as f32;
+            if age > span || tick < 0 {
+                break;
             }
-            format!("{:.2} MB", memory_usage)
+            let time = match self.time_axis {
+                TimeAxis::Wall => Local.timestamp_opt(tick, 0).single(),
+                TimeAxis::Elapsed => Some(self.first_sample + chrono::Duration::seconds(tick)),
+            };
+            if let Some(time) = time {
+                let label = format!("^{}", self.time_axis.format(time, self.first_sample));
+                let column = (age / span * columns as f32) as usize;
+                for (offset, char) in label.chars().enumerate() {
+                    if let Some(cell) = line.get_mut(column + offset) {
+                        *cell = char;
+                    }
+                }
+            }
+            tick -= step;
+        }
+        line.into_iter().collect()
+    }
+
+    fn scale(&self, value: f32) -> f32 {
+        if self.log_scale {
+            (1. + value.max(0.)).log10()
+        } else {
+            value
+        }
+    }
+
+    fn scale_points(&self, data: &[(f32, f32)], factor: f32) -> Vec<(f32, f32)> {
+        self.resample(data)
+            .iter()
+            .map(|&(x, value)| (x, self.scale(value) * factor))
+            .collect()
+    }
+
+    // Shrinks the history to at most one point per dot column. Each column keeps
+    // its highest point, so short spikes do not disappear on a narrow chart
+    fn resample(&self, data: &[(f32, f32)]) -> Vec<(f32, f32)> {
+        let columns = self.width.max(1) as usize;
+        if data.len() <= columns {
+            return data.to_vec();
+        }
+        data.chunks(data.len().div_ceil(columns))
+            .map(|chunk| {
+                chunk.iter().copied().fold(
+                    chunk[0],
+                    |peak, point| if point.1 > peak.1 { point } else { peak },
+                )
+            })
+            .collect()
+    }
+
+    // Function to draw the memory of individual children in a separate panel
+    pub fn draw_children_chart(&self) {
+        self.draw_panel(&self.children_data, &self.children_label, "MB");
+    }
+
+    // Function to draw the selected detail series in a separate panel
+    pub fn draw_details_chart(&self) {
+        self.draw_panel(&self.details_data, &self.details_label, "");
+    }
+
+    fn draw_panel(&self, series: &[(Vec<(f32, f32)>, RGB8)], label: &str, unit: &'static str) {
+        let max = self.scale(
+            series
+                .iter()
+                .flat_map(|(data, _)| data.iter().map(|&(_, value)| value))
+                .fold(1., f32::max),
+        );
+        // Дочерние процессы появляются в разное время, и их истории разной длины,
+        // поэтому видимую часть считаем для каждой линии отдельно
+        let span = series
+            .iter()
+            .map(|(data, _)| self.visible(data).1)
+            .fold(1., f32::max);
+
+        let mut chart = Chart::new_with_y_range(self.width, self.height, 0., span, 0., max);
+        chart.axis();
+        chart.figures();
+
+        let lines: Vec<(Vec<(f32, f32)>, RGB8)> = series
+            .iter()
+            .map(|(data, color)| {
+                let start = self.visible(data).0;
+                (self.scale_points(&data[start..], 1.), *color)
+            })
+            .collect();
+        let shapes: Vec<(Shape, RGB8)> = lines
+            .iter()
+            .map(|(data, color)| (Shape::Lines(data), *color))
+            .collect();
+
+        let mut dots = &mut chart;
+        for (shape, color) in &shapes {
+            dots = dots.linecolorplot(shape, *color);
+        }
+
+        let xlabel = label.to_string();
+        dots = dots.x_label_format(LabelFormat::Custom(Box::new(move |val| {
+            if val > 0. {
+                return String::new();
+            }
+            xlabel.clone()
+        })));
+        let log_scale = self.log_scale;
+        dots = dots.y_label_format(LabelFormat::Custom(Box::new(move |val| {
+            format!("{:.2} {}", unscale(val, log_scale), unit)
         })));
 
-        // Display the chart
         dots.display();
+        println!("{}", self.time_ticks(span));
     }
 }
+
+// Inverse of `ChartManager::scale`, used for the axis labels
+fn unscale(value: f32, log_scale: bool) -> f32 {
+    if log_scale {
+        10f32.powf(value) - 1.
+    } else {
+        value
+    }
+}
+
+fn last_value(data: &[(f32, f32)]) -> f32 {
+    data.last().map_or(0., |&(_, value)| value)
+}
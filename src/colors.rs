@@ -0,0 +1,20 @@
+pub const RED: rgb::RGB8 = rgb::RGB8::new(0xFF, 0x00, 0x00);
+pub const GREEN: rgb::RGB8 = rgb::RGB8::new(0x00, 0xFF, 0x00);
+pub const PURPLE: rgb::RGB8 = rgb::RGB8::new(0xE0, 0x80, 0xFF);
+pub const BLUE: rgb::RGB8 = rgb::RGB8::new(0x00, 0x00, 0xFF);
+pub const ORANGE: rgb::RGB8 = rgb::RGB8::new(0xFF, 0xA5, 0x00);
+pub const YELLOW: rgb::RGB8 = rgb::RGB8::new(0xFF, 0xFF, 0x00);
+pub const CYAN: rgb::RGB8 = rgb::RGB8::new(0x00, 0xFF, 0xFF);
+pub const MAGENTA: rgb::RGB8 = rgb::RGB8::new(0xFF, 0x00, 0xFF);
+pub const WHITE: rgb::RGB8 = rgb::RGB8::new(0xFF, 0xFF, 0xFF);
+pub const TEAL: rgb::RGB8 = rgb::RGB8::new(0x00, 0x80, 0x80);
+pub const PINK: rgb::RGB8 = rgb::RGB8::new(0xFF, 0xC0, 0xCB);
+pub const SKY: rgb::RGB8 = rgb::RGB8::new(0x87, 0xCE, 0xFA);
+pub const LAVENDER: rgb::RGB8 = rgb::RGB8::new(0xB0, 0xA0, 0xFF);
+pub const SILVER: rgb::RGB8 = rgb::RGB8::new(0xA0, 0xA0, 0xA0);
+pub const LIME: rgb::RGB8 = rgb::RGB8::new(0xBF, 0xFF, 0x00);
+pub const GOLD: rgb::RGB8 = rgb::RGB8::new(0xD4, 0xAF, 0x37);
+pub const CORAL: rgb::RGB8 = rgb::RGB8::new(0xFF, 0x7F, 0x50);
+pub const OLIVE: rgb::RGB8 = rgb::RGB8::new(0x80, 0x80, 0x00);
+pub const MINT: rgb::RGB8 = rgb::RGB8::new(0x98, 0xFF, 0x98);
+pub const BROWN: rgb::RGB8 = rgb::RGB8::new(0xA5, 0x5A, 0x2A);
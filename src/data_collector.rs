@@ -1,31 +1,201 @@
-use crate::PRINT_LEN;
-use std::collections::HashSet;
-use sysinfo::{Pid, System};
+use crate::cgroup;
+use crate::details::{self, ProcStats};
+use crate::history::History;
+use crate::net::NetStats;
+use crate::selector::cmdline;
+use crate::series::{self, REGISTRY};
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::time::Instant;
+use sysinfo::{Pid, ProcessStatus, System};
+
+// Raw samples kept per series unless `--history` says otherwise
+pub const DEFAULT_HISTORY: usize = 500;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum CpuMode {
+    // Percent of one core, summed over the tree (can exceed 100%)
+    PerCore,
+    // Percent of all logical cores together (0-100%)
+    Normalized,
+}
 
 pub struct DataCollector {
-    pub(crate) cpu_data: [(f32, f32); PRINT_LEN],
-    pub(crate) memory_data: [(f32, f32); PRINT_LEN],
-    pub(crate) disk_write_data: [(f32, f32); PRINT_LEN],
-    pub(crate) disk_read_data: [(f32, f32); PRINT_LEN],
+    // Number of raw samples every series keeps before rolling them up
+    history: usize,
+    // One history per series of `REGISTRY`, in the same order
+    series: Vec<History>,
+
+    // Time of the first and of the latest sample
+    first_sample: Option<DateTime<Local>>,
+    last_sample: Option<DateTime<Local>>,
+    // Seconds from the first to the latest sample, the time the series are pushed with
+    elapsed: f64,
+
+    // Root process (PID, start time) the I/O counters below belong to
+    io_root: Option<(Pid, u64)>,
+    // Highest (read, written) MB of the current root tree. Reaped children are
+    // added to their parent's counters by the kernel, so the sum of the live
+    // processes already includes them and only has to be kept monotonic
+    io_live: (f32, f32),
+    // (read, written) MB of previous roots, e.g. before a restart in watch mode
+    io_carried: (f32, f32),
+    // Time and totals (MB) of the previous sample, used for throughput
+    last_io: Option<(Instant, f32, f32)>,
+
+    // (read, written) bytes of io.stat when a cgroup was first sampled
+    cgroup_io_base: Option<(u64, u64)>,
+    // Time and usage_usec of cpu.stat of the previous cgroup sample
+    last_cgroup_cpu: Option<(Instant, u64)>,
+    // Time and /proc counters of the previous sample, used for their rates
+    last_details: Option<(Instant, ProcStats)>,
+
+    pub cpu_mode: CpuMode,
+    // PSS and USS are only read when they are charted or exported
+    pub smaps: bool,
+    // The same for context switches, which are read from every thread
+    pub ctx_switches: bool,
+
+    // Usage (% of the core) each core spent on the tree's threads, newest last.
+    // Only collected when `heatmap` is set
+    pub heatmap: bool,
+    core_data: VecDeque<Vec<f32>>,
+    // Time and CPU time (seconds) of every thread at the previous sample
+    last_threads: Option<(Instant, HashMap<u32, f32>)>,
+
+    // Network metrics are only collected when enabled, reading the socket
+    // tables on every tick is not free
+    pub net: bool,
+    // (rx, tx) bytes of the namespaces when they were first sampled
+    net_base: Option<(u64, u64)>,
+    // Time and (rx, tx) MB of the previous sample, used for throughput
+    last_net: Option<(Instant, f32, f32)>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ProcessData {
     pub cpu_usage: f32,
     pub memory_usage: f32,
+    // Lifetime disk I/O of the tree, MB
     pub total_written_bytes: f32,
     pub total_read_bytes: f32,
+    // Disk throughput of the tree, MB/s
+    pub disk_write_rate: f32,
+    pub disk_read_rate: f32,
+    pub name: String,
+    pub status: String,
+    pub children: usize,
+    // Details from /proc (Linux only), summed over the tree. Sizes in MB
+    #[serde(default)]
+    pub virtual_memory: f32,
+    #[serde(default)]
+    pub swap: f32,
+    #[serde(default)]
+    pub pss: f32,
+    #[serde(default)]
+    pub uss: f32,
+    #[serde(default)]
+    pub threads: u64,
+    #[serde(default)]
+    pub fds: u64,
+    // Context switches and page faults per second
+    #[serde(default)]
+    pub voluntary_ctx_switches: f32,
+    #[serde(default)]
+    pub involuntary_ctx_switches: f32,
+    #[serde(default)]
+    pub minor_faults: f32,
+    #[serde(default)]
+    pub major_faults: f32,
+    // CPU time spent in user and kernel mode, % like `cpu_usage`
+    #[serde(default)]
+    pub user_cpu: f32,
+    #[serde(default)]
+    pub system_cpu: f32,
+    // Sockets of the tree, all of them and TCP ones by state
+    #[serde(default)]
+    pub sockets: u64,
+    #[serde(default)]
+    pub tcp_established: u64,
+    #[serde(default)]
+    pub tcp_listen: u64,
+    #[serde(default)]
+    pub tcp_time_wait: u64,
+    #[serde(default)]
+    pub tcp_close_wait: u64,
+    // Traffic of the tree's network namespaces since the start, MB, and its throughput, MB/s
+    #[serde(default)]
+    pub total_net_rx: f32,
+    #[serde(default)]
+    pub total_net_tx: f32,
+    #[serde(default)]
+    pub net_rx_rate: f32,
+    #[serde(default)]
+    pub net_tx_rate: f32,
+    // Own values of every process of the tree, root first (depth-first order).
+    // Recorded as well, so replays show the tree panel and the top children
+    #[serde(default)]
+    pub tree: Vec<ProcessNode>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessNode {
+    #[serde(with = "pid_number")]
+    pub pid: Pid,
+    pub depth: usize,
     pub name: String,
+    pub cpu_usage: f32,
+    pub memory_usage: f32,
     pub status: String,
 }
 
+// `Pid` has no serde support, recordings store it as a plain number
+mod pid_number {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use sysinfo::Pid;
+
+    pub fn serialize<S: Serializer>(pid: &Pid, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(pid.as_u32())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pid, D::Error> {
+        u32::deserialize(deserializer).map(Pid::from_u32)
+    }
+}
+
+impl Default for DataCollector {
+    fn default() -> Self {
+        DataCollector::new()
+    }
+}
+
 impl DataCollector {
     pub fn new() -> Self {
         DataCollector {
-            cpu_data: [(0., 0.); PRINT_LEN],
-            memory_data: [(0., 0.); PRINT_LEN],
-            disk_write_data: [(0., 0.); PRINT_LEN],
-            disk_read_data: [(0., 0.); PRINT_LEN],
+            history: DEFAULT_HISTORY,
+            series: vec![History::new(DEFAULT_HISTORY); REGISTRY.len()],
+            first_sample: None,
+            last_sample: None,
+            elapsed: 0.,
+            io_root: None,
+            io_live: (0., 0.),
+            io_carried: (0., 0.),
+            last_io: None,
+            cgroup_io_base: None,
+            last_cgroup_cpu: None,
+            last_details: None,
+            cpu_mode: CpuMode::PerCore,
+            smaps: false,
+            ctx_switches: false,
+            heatmap: false,
+            core_data: VecDeque::new(),
+            last_threads: None,
+            net: false,
+            net_base: None,
+            last_net: None,
         }
     }
 
@@ -33,7 +203,10 @@ impl DataCollector {
         &self,
         system: &System,
         pid: Pid,
+        depth: usize,
         visited: &mut HashSet<Pid>,
+        tree: &mut Vec<ProcessNode>,
+        stats: &mut ProcStats,
     ) -> ProcessData {
         let mut total_cpu_usage = 0.0;
         let mut total_memory_usage = 0.0;
@@ -41,44 +214,54 @@ impl DataCollector {
         let mut total_read_bytes = 0.0;
         let mut name = String::new();
         let mut status = String::new();
+        let mut children = 0;
 
         if visited.contains(&pid) {
-            return ProcessData {
-                cpu_usage: 0.0,
-                memory_usage: 0.0,
-                total_written_bytes: 0.0,
-                total_read_bytes: 0.0,
-                name: String::new(),
-                status: String::new(),
-            };
+            return ProcessData::default();
         }
         visited.insert(pid);
 
         if let Some(proc) = system.process(pid) {
             // Добавляем данные текущего процесса
-            total_cpu_usage += proc.cpu_usage() as f32;
+            total_cpu_usage += proc.cpu_usage();
             total_memory_usage += proc.memory() as f32 / 1024.0 / 1024.0;
-            total_written_bytes += proc.disk_usage().written_bytes as f32 / 1024.0 / 1024.0;
-            total_read_bytes += proc.disk_usage().read_bytes as f32 / 1024.0 / 1024.0;
-            name = proc
-                .cmd()
-                .iter()
-                .map(|s| s.to_string_lossy())
-                .collect::<Vec<_>>()
-                .join(" ");
+            // Счётчики зомби уже могут быть учтены у родителя
+            if proc.status() != ProcessStatus::Zombie {
+                total_written_bytes +=
+                    proc.disk_usage().total_written_bytes as f32 / 1024.0 / 1024.0;
+                total_read_bytes += proc.disk_usage().total_read_bytes as f32 / 1024.0 / 1024.0;
+                *stats += ProcStats::read(pid, self.smaps, self.ctx_switches);
+            }
+            name = cmdline(proc);
             status = proc.status().to_string();
+            tree.push(ProcessNode {
+                pid,
+                depth,
+                name: name.clone(),
+                cpu_usage: total_cpu_usage,
+                memory_usage: total_memory_usage,
+                status: status.clone(),
+            });
 
-            // Рекурсивно собираем данные о дочерних процессах
+            // Рекурсивно собираем данные о дочерних процессах, потоки уже учтены в процессе
             for child_pid in system
                 .processes()
                 .values()
-                .filter(|p| p.parent().map_or(false, |parent_pid| parent_pid == pid))
+                .filter(|p| p.parent() == Some(pid) && p.thread_kind().is_none())
             {
-                let child_data = self.collect_process_data(system, child_pid.pid(), visited);
+                let child_data = self.collect_process_data(
+                    system,
+                    child_pid.pid(),
+                    depth + 1,
+                    visited,
+                    tree,
+                    stats,
+                );
                 total_cpu_usage += child_data.cpu_usage;
                 total_memory_usage += child_data.memory_usage;
                 total_written_bytes += child_data.total_written_bytes;
                 total_read_bytes += child_data.total_read_bytes;
+                children += child_data.children + 1;
             }
         }
 
@@ -89,54 +272,296 @@ impl DataCollector {
             total_read_bytes,
             name,
             status,
+            children,
+            ..Default::default()
         }
     }
 
-    pub fn get_process_data(&self, system: &System, pid: Pid) -> Option<ProcessData> {
+    pub fn get_process_data(&mut self, system: &System, pid: Pid) -> Option<ProcessData> {
+        let root = (pid, system.process(pid)?.start_time());
+
         // Собираем данные для указанного PID и всех его дочерних процессов
         let mut visited = HashSet::new();
-        let process_data = self.collect_process_data(system, pid, &mut visited);
+        let mut tree = Vec::new();
+        let mut stats = ProcStats::default();
+        let mut process_data =
+            self.collect_process_data(system, pid, 0, &mut visited, &mut tree, &mut stats);
+        process_data.tree = tree;
+        self.set_details(&mut process_data, stats);
+        self.set_net(&mut process_data);
+        self.set_cpu(system, &mut process_data);
+
+        // Диск считаем по накопленным счётчикам, а не по разнице с прошлого обновления
+        if self.io_root != Some(root) {
+            if self.io_root.is_some() {
+                self.io_carried.0 += self.io_live.0;
+                self.io_carried.1 += self.io_live.1;
+            }
+            self.io_root = Some(root);
+            self.io_live = (0., 0.);
+        }
+        self.io_live.0 = self.io_live.0.max(process_data.total_read_bytes);
+        self.io_live.1 = self.io_live.1.max(process_data.total_written_bytes);
+        let total_read_bytes = self.io_carried.0 + self.io_live.0;
+        let total_written_bytes = self.io_carried.1 + self.io_live.1;
 
+        self.set_io(&mut process_data, total_read_bytes, total_written_bytes);
         Some(process_data)
     }
 
-    pub fn update_cpu_data(&mut self, new_value: f32) -> &mut DataCollector {
-        let len = self.cpu_data.len();
-        self.cpu_data.copy_within(1..len, 0);
-        self.cpu_data[len - 1] = (0., new_value);
-        for point in self.cpu_data.iter_mut() {
-            point.0 += 1.;
+    // Collects the totals of every process in a cgroup v2 directory. The
+    // cgroup's own counters are used where available, since they also cover
+    // processes that already exited; otherwise the processes are summed up
+    pub fn get_cgroup_data(&mut self, system: &System, path: &Path) -> Option<ProcessData> {
+        let cgroup_stats = cgroup::read(path).ok()?;
+
+        let mut details = ProcStats::default();
+        let mut process_data = ProcessData {
+            name: path.display().to_string(),
+            status: String::from(if cgroup_stats.pids.is_empty() {
+                "Empty"
+            } else {
+                "Active"
+            }),
+            children: cgroup_stats.pids.len(),
+            ..Default::default()
+        };
+        for &pid in &cgroup_stats.pids {
+            let Some(proc) = system.process(pid) else {
+                continue;
+            };
+            let cpu_usage = proc.cpu_usage();
+            let memory_usage = proc.memory() as f32 / 1024.0 / 1024.0;
+            process_data.cpu_usage += cpu_usage;
+            process_data.memory_usage += memory_usage;
+            if proc.status() != ProcessStatus::Zombie {
+                process_data.total_written_bytes +=
+                    proc.disk_usage().total_written_bytes as f32 / 1024.0 / 1024.0;
+                process_data.total_read_bytes +=
+                    proc.disk_usage().total_read_bytes as f32 / 1024.0 / 1024.0;
+                details += ProcStats::read(pid, self.smaps, self.ctx_switches);
+            }
+            process_data.tree.push(ProcessNode {
+                pid,
+                depth: 0,
+                name: cmdline(proc),
+                cpu_usage,
+                memory_usage,
+                status: proc.status().to_string(),
+            });
         }
 
-        self
+        let now = Instant::now();
+        if let Some(usage_usec) = cgroup_stats.cpu_usage_usec {
+            if let Some((last_time, last_usec)) = self.last_cgroup_cpu {
+                let seconds = now.duration_since(last_time).as_secs_f32();
+                if seconds > 0. {
+                    process_data.cpu_usage =
+                        usage_usec.saturating_sub(last_usec) as f32 / 1_000_000.0 / seconds * 100.;
+                }
+            }
+            self.last_cgroup_cpu = Some((now, usage_usec));
+        }
+        if let Some(memory_bytes) = cgroup_stats.memory_bytes {
+            process_data.memory_usage = memory_bytes as f32 / 1024.0 / 1024.0;
+        }
+
+        let (total_read_bytes, total_written_bytes) = match cgroup_stats.io_bytes {
+            // Считаем с начала мониторинга, а не с создания группы
+            Some((read, written)) => {
+                let base = *self.cgroup_io_base.get_or_insert((read, written));
+                (
+                    read.saturating_sub(base.0) as f32 / 1024.0 / 1024.0,
+                    written.saturating_sub(base.1) as f32 / 1024.0 / 1024.0,
+                )
+            }
+            None => {
+                self.io_live.0 = self.io_live.0.max(process_data.total_read_bytes);
+                self.io_live.1 = self.io_live.1.max(process_data.total_written_bytes);
+                self.io_live
+            }
+        };
+
+        self.set_io(&mut process_data, total_read_bytes, total_written_bytes);
+        self.set_details(&mut process_data, details);
+        self.set_net(&mut process_data);
+        self.set_cpu(system, &mut process_data);
+        Some(process_data)
     }
 
-    pub fn update_memory_data(&mut self, new_value: f32) -> &mut DataCollector {
-        let len = self.memory_data.len();
-        self.memory_data.copy_within(1..len, 0);
-        self.memory_data[len - 1] = (0., new_value);
-        for point in self.memory_data.iter_mut() {
-            point.0 += 1.;
+    // Stores the lifetime totals (MB) in the sample and derives the throughput
+    fn set_io(
+        &mut self,
+        process_data: &mut ProcessData,
+        total_read_bytes: f32,
+        total_written_bytes: f32,
+    ) {
+        let now = Instant::now();
+        if let Some((last_time, last_read, last_written)) = self.last_io {
+            let seconds = now.duration_since(last_time).as_secs_f32();
+            if seconds > 0. {
+                process_data.disk_read_rate = (total_read_bytes - last_read).max(0.) / seconds;
+                process_data.disk_write_rate =
+                    (total_written_bytes - last_written).max(0.) / seconds;
+            }
         }
-        self
+        self.last_io = Some((now, total_read_bytes, total_written_bytes));
+        process_data.total_read_bytes = total_read_bytes;
+        process_data.total_written_bytes = total_written_bytes;
+    }
+
+    // Copies the /proc values into the sample and turns the counters into rates
+    fn set_details(&mut self, process_data: &mut ProcessData, details: ProcStats) {
+        process_data.virtual_memory = details.virtual_memory;
+        process_data.swap = details.swap;
+        process_data.pss = details.pss;
+        process_data.uss = details.uss;
+        process_data.threads = details.threads;
+        process_data.fds = details.fds;
+
+        let now = Instant::now();
+        if let Some((last_time, last)) = self.last_details {
+            let seconds = now.duration_since(last_time).as_secs_f32();
+            if seconds > 0. {
+                // Счётчики завершившихся потомков пропадают, поэтому разница может быть отрицательной
+                let rate =
+                    |current: u64, previous: u64| current.saturating_sub(previous) as f32 / seconds;
+                process_data.voluntary_ctx_switches =
+                    rate(details.voluntary_ctx_switches, last.voluntary_ctx_switches);
+                process_data.involuntary_ctx_switches = rate(
+                    details.involuntary_ctx_switches,
+                    last.involuntary_ctx_switches,
+                );
+                process_data.minor_faults = rate(details.minor_faults, last.minor_faults);
+                process_data.major_faults = rate(details.major_faults, last.major_faults);
+                process_data.user_cpu =
+                    (details.user_time - last.user_time).max(0.) / seconds * 100.;
+                process_data.system_cpu =
+                    (details.system_time - last.system_time).max(0.) / seconds * 100.;
+            }
+        }
+        self.last_details = Some((now, details));
     }
 
-    pub fn update_disk_write_data(&mut self, new_value: f32) -> &mut DataCollector {
-        let len = self.disk_write_data.len();
-        self.disk_write_data.copy_within(1..len, 0);
-        self.disk_write_data[len - 1] = (0., new_value);
-        for point in self.disk_write_data.iter_mut() {
-            point.0 += 1.;
+    // Applies the CPU mode and samples the per-core usage of the tree's threads
+    fn set_cpu(&mut self, system: &System, process_data: &mut ProcessData) {
+        let cores = system.cpus().len().max(1);
+
+        if self.heatmap {
+            let pids: Vec<Pid> = process_data.tree.iter().map(|node| node.pid).collect();
+            let threads = details::thread_times(&pids);
+            let now = Instant::now();
+            let mut per_core = vec![0.; cores];
+            if let Some((last_time, ref last)) = self.last_threads {
+                let seconds = now.duration_since(last_time).as_secs_f32();
+                for &(tid, core, time) in &threads {
+                    // Новые потоки учитываем со следующего замера
+                    let Some(&last_time) = last.get(&tid) else {
+                        continue;
+                    };
+                    if seconds > 0. && core < cores {
+                        per_core[core] += (time - last_time).max(0.) / seconds * 100.;
+                    }
+                }
+            }
+            self.last_threads = Some((
+                now,
+                threads.iter().map(|&(tid, _, time)| (tid, time)).collect(),
+            ));
+            self.core_data.push_back(per_core);
+            if self.core_data.len() > self.history {
+                self.core_data.pop_front();
+            }
+        }
+
+        if self.cpu_mode == CpuMode::Normalized {
+            let cores = cores as f32;
+            process_data.cpu_usage /= cores;
+            process_data.user_cpu /= cores;
+            process_data.system_cpu /= cores;
+            for node in process_data.tree.iter_mut() {
+                node.cpu_usage /= cores;
+            }
         }
+    }
+
+    // Reads the sockets and traffic of the sampled tree, if enabled
+    fn set_net(&mut self, process_data: &mut ProcessData) {
+        if !self.net {
+            return;
+        }
+        let pids: Vec<Pid> = process_data.tree.iter().map(|node| node.pid).collect();
+        let net = NetStats::read(&pids);
+        process_data.sockets = net.sockets;
+        process_data.tcp_established = net.established;
+        process_data.tcp_listen = net.listen;
+        process_data.tcp_time_wait = net.time_wait;
+        process_data.tcp_close_wait = net.close_wait;
+
+        let base = *self.net_base.get_or_insert((net.rx_bytes, net.tx_bytes));
+        let total_rx = net.rx_bytes.saturating_sub(base.0) as f32 / 1024.0 / 1024.0;
+        let total_tx = net.tx_bytes.saturating_sub(base.1) as f32 / 1024.0 / 1024.0;
+        let now = Instant::now();
+        if let Some((last_time, last_rx, last_tx)) = self.last_net {
+            let seconds = now.duration_since(last_time).as_secs_f32();
+            if seconds > 0. {
+                process_data.net_rx_rate = (total_rx - last_rx).max(0.) / seconds;
+                process_data.net_tx_rate = (total_tx - last_tx).max(0.) / seconds;
+            }
+        }
+        self.last_net = Some((now, total_rx, total_tx));
+        process_data.total_net_rx = total_rx;
+        process_data.total_net_tx = total_tx;
+    }
+
+    // Number of raw samples every series keeps
+    pub fn history(&self) -> usize {
+        self.history
+    }
+
+    // History of the series with this name, see `REGISTRY`
+    pub fn series(&self, name: &str) -> Option<&History> {
+        series::index(name).map(|index| &self.series[index])
+    }
+
+    pub fn first_sample(&self) -> Option<DateTime<Local>> {
+        self.first_sample
+    }
+
+    pub fn last_sample(&self) -> Option<DateTime<Local>> {
+        self.last_sample
+    }
+
+    // Seconds from the first to the latest sample
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    // Per-core usage of the tree's threads, newest last (only with `heatmap`)
+    pub fn core_data(&self) -> &VecDeque<Vec<f32>> {
+        &self.core_data
+    }
+
+    // Sets the number of raw samples kept per series, drops the collected history
+    pub fn set_history(&mut self, history: usize) -> &mut DataCollector {
+        self.history = history;
+        self.series = vec![History::new(history); REGISTRY.len()];
+        self.core_data.clear();
+        self
+    }
+
+    // Stamps the sample the following `update_*` calls belong to
+    pub fn set_sample_time(&mut self, time: DateTime<Local>) -> &mut DataCollector {
+        let first_sample = *self.first_sample.get_or_insert(time);
+        self.elapsed = (time - first_sample).num_milliseconds().max(0) as f64 / 1000.;
+        self.last_sample = Some(time);
         self
     }
 
-    pub fn update_disk_read_data(&mut self, new_value: f32) -> &mut DataCollector {
-        let len = self.disk_read_data.len();
-        self.disk_read_data.copy_within(1..len, 0);
-        self.disk_read_data[len - 1] = (0., new_value);
-        for point in self.disk_read_data.iter_mut() {
-            point.0 += 1.;
+    // Pushes the values of all registered series
+    pub fn update_series(&mut self, process_data: &ProcessData) -> &mut DataCollector {
+        for (history, spec) in self.series.iter_mut().zip(&REGISTRY) {
+            history.push(self.elapsed, (spec.value)(process_data));
         }
         self
     }
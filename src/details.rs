@@ -0,0 +1,197 @@
+use std::ops::AddAssign;
+use sysinfo::Pid;
+
+// Raw per-process values from /proc, summed over the tree. Counters and CPU
+// times are cumulative, the data collector turns them into rates
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProcStats {
+    // MB
+    pub virtual_memory: f32,
+    pub swap: f32,
+    pub pss: f32,
+    pub uss: f32,
+    pub threads: u64,
+    pub fds: u64,
+    pub voluntary_ctx_switches: u64,
+    pub involuntary_ctx_switches: u64,
+    pub minor_faults: u64,
+    pub major_faults: u64,
+    // Seconds
+    pub user_time: f32,
+    pub system_time: f32,
+}
+
+impl AddAssign for ProcStats {
+    fn add_assign(&mut self, other: ProcStats) {
+        self.virtual_memory += other.virtual_memory;
+        self.swap += other.swap;
+        self.pss += other.pss;
+        self.uss += other.uss;
+        self.threads += other.threads;
+        self.fds += other.fds;
+        self.voluntary_ctx_switches += other.voluntary_ctx_switches;
+        self.involuntary_ctx_switches += other.involuntary_ctx_switches;
+        self.minor_faults += other.minor_faults;
+        self.major_faults += other.major_faults;
+        self.user_time += other.user_time;
+        self.system_time += other.system_time;
+    }
+}
+
+impl ProcStats {
+    // Values the process does not let us read (e.g. smaps of another user) stay 0.
+    // PSS and USS come from smaps_rollup, which is costly on large processes and
+    // is only read with `smaps`. Context switches need the status of every thread
+    // and are only read with `ctx_switches`
+    #[cfg(target_os = "linux")]
+    pub fn read(pid: Pid, smaps: bool, ctx_switches: bool) -> Self {
+        use std::fs;
+
+        let dir = format!("/proc/{}", pid);
+        let mut stats = ProcStats::default();
+        let kb_to_mb = |value: &str| {
+            value
+                .trim()
+                .trim_end_matches("kB")
+                .trim()
+                .parse::<f32>()
+                .unwrap_or(0.)
+                / 1024.
+        };
+
+        if let Ok(status) = fs::read_to_string(format!("{}/status", dir)) {
+            for line in status.lines() {
+                let Some((key, value)) = line.split_once(':') else {
+                    continue;
+                };
+                let count = || value.trim().parse().unwrap_or(0);
+                match key {
+                    "VmSize" => stats.virtual_memory = kb_to_mb(value),
+                    "VmSwap" => stats.swap = kb_to_mb(value),
+                    "Threads" => stats.threads = count(),
+                    _ => {}
+                }
+            }
+        }
+
+        // Переключения контекста в status процесса - только главного потока,
+        // поэтому суммируем их по всем потокам из task/
+        let tasks = if ctx_switches {
+            fs::read_dir(format!("{}/task", dir)).ok()
+        } else {
+            None
+        };
+        if let Some(tasks) = tasks {
+            for task in tasks.flatten() {
+                let Ok(status) = fs::read_to_string(task.path().join("status")) else {
+                    continue;
+                };
+                for line in status.lines() {
+                    let Some((key, value)) = line.split_once(':') else {
+                        continue;
+                    };
+                    let count = || value.trim().parse().unwrap_or(0);
+                    match key {
+                        "voluntary_ctxt_switches" => stats.voluntary_ctx_switches += count(),
+                        "nonvoluntary_ctxt_switches" => stats.involuntary_ctx_switches += count(),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let rollup = if smaps {
+            fs::read_to_string(format!("{}/smaps_rollup", dir))
+        } else {
+            Ok(String::new())
+        };
+        if let Ok(rollup) = rollup {
+            for line in rollup.lines() {
+                let Some((key, value)) = line.split_once(':') else {
+                    continue;
+                };
+                match key {
+                    "Pss" => stats.pss = kb_to_mb(value),
+                    "Private_Clean" | "Private_Dirty" => stats.uss += kb_to_mb(value),
+                    _ => {}
+                }
+            }
+        }
+
+        if let Ok(entries) = fs::read_dir(format!("{}/fd", dir)) {
+            stats.fds = entries.count() as u64;
+        }
+
+        // Имя процесса в скобках может содержать пробелы, поля считаем после него
+        if let Ok(stat) = fs::read_to_string(format!("{}/stat", dir)) {
+            if let Some((_, fields)) = stat.rsplit_once(')') {
+                let fields: Vec<&str> = fields.split_whitespace().collect();
+                let field = |index: usize| -> u64 {
+                    fields
+                        .get(index)
+                        .and_then(|value| value.parse().ok())
+                        .unwrap_or(0)
+                };
+                let ticks = clock_ticks();
+                stats.minor_faults = field(7);
+                stats.major_faults = field(9);
+                stats.user_time = field(11) as f32 / ticks;
+                stats.system_time = field(12) as f32 / ticks;
+            }
+        }
+
+        stats
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn read(_pid: Pid, _smaps: bool, _ctx_switches: bool) -> Self {
+        ProcStats::default()
+    }
+}
+
+// CPU time (seconds) and the core each thread of the processes last ran on
+#[cfg(target_os = "linux")]
+pub fn thread_times(pids: &[Pid]) -> Vec<(u32, usize, f32)> {
+    use std::fs;
+
+    let ticks = clock_ticks();
+    let mut threads = Vec::new();
+    for pid in pids {
+        let Ok(tasks) = fs::read_dir(format!("/proc/{}/task", pid)) else {
+            continue;
+        };
+        for task in tasks.flatten() {
+            let Ok(tid) = task.file_name().to_string_lossy().parse::<u32>() else {
+                continue;
+            };
+            let Ok(stat) = fs::read_to_string(task.path().join("stat")) else {
+                continue;
+            };
+            let Some((_, fields)) = stat.rsplit_once(')') else {
+                continue;
+            };
+            let fields: Vec<&str> = fields.split_whitespace().collect();
+            let field = |index: usize| -> u64 {
+                fields
+                    .get(index)
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(0)
+            };
+            // После имени: utime и stime - поля 11 и 12, processor - поле 36
+            let time = (field(11) + field(12)) as f32 / ticks;
+            threads.push((tid, field(36) as usize, time));
+        }
+    }
+    threads
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn thread_times(_pids: &[Pid]) -> Vec<(u32, usize, f32)> {
+    Vec::new()
+}
+
+#[cfg(target_os = "linux")]
+fn clock_ticks() -> f32 {
+    // SAFETY: sysconf only reads a system constant
+    unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as f32
+}
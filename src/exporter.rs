@@ -0,0 +1,213 @@
+use chrono::Local;
+use clap::ValueEnum;
+use monit_tool::data_collector::ProcessData;
+use monit_tool::series::{SeriesSpec, REGISTRY};
+use std::fs::File;
+use std::io::{self, LineWriter, Write};
+use std::path::Path;
+use sysinfo::Pid;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+}
+
+// One exported value
+enum Value<'a> {
+    Text(&'a str),
+    Count(u64),
+    // Value and the number of decimals written to CSV
+    Number(f32, usize),
+}
+
+impl Value<'_> {
+    fn csv(&self) -> String {
+        match *self {
+            Value::Text(text) => text.to_string(),
+            Value::Count(count) => count.to_string(),
+            Value::Number(number, decimals) => format!("{:.*}", decimals, number),
+        }
+    }
+
+    fn json(&self) -> serde_json::Result<String> {
+        match *self {
+            Value::Text(text) => serde_json::to_string(text),
+            Value::Count(count) => Ok(count.to_string()),
+            Value::Number(number, _) => serde_json::to_string(&number),
+        }
+    }
+}
+
+// Columns that are not series of `REGISTRY`: totals and TCP sockets by state
+type Extra = (&'static str, fn(&ProcessData) -> Value<'static>);
+
+const EXTRA: [Extra; 8] = [
+    ("disk_read_mb", |data| {
+        Value::Number(data.total_read_bytes, 4)
+    }),
+    ("disk_write_mb", |data| {
+        Value::Number(data.total_written_bytes, 4)
+    }),
+    ("net_rx_mb", |data| Value::Number(data.total_net_rx, 4)),
+    ("net_tx_mb", |data| Value::Number(data.total_net_tx, 4)),
+    ("tcp_established", |data| Value::Count(data.tcp_established)),
+    ("tcp_listen", |data| Value::Count(data.tcp_listen)),
+    ("tcp_time_wait", |data| Value::Count(data.tcp_time_wait)),
+    ("tcp_close_wait", |data| Value::Count(data.tcp_close_wait)),
+];
+
+// Column of a series: its name with the unit, e.g. `disk-read` in MB/s is `disk_read_mb_s`
+fn column(spec: &SeriesSpec) -> String {
+    let unit: String = spec
+        .unit
+        .to_lowercase()
+        .chars()
+        .filter_map(|char| match char {
+            '/' => Some('_'),
+            char if char.is_alphanumeric() => Some(char),
+            _ => None,
+        })
+        .collect();
+    let name = spec.name.replace('-', "_");
+    match unit.as_str() {
+        "" => name,
+        unit if unit.starts_with('_') => name + unit,
+        unit => format!("{}_{}", name, unit),
+    }
+}
+
+// Value of a series; unitless series without decimals are counts
+fn value(spec: &SeriesSpec, process_data: &ProcessData) -> Value<'static> {
+    let value = (spec.value)(process_data);
+    if spec.unit.is_empty() && spec.decimals == 0 {
+        Value::Count(value as u64)
+    } else {
+        Value::Number(value, spec.decimals + 2)
+    }
+}
+
+// Writes one machine-readable record per tick, independent of the chart.
+// Every series of `REGISTRY` gets a column, so new series are exported as well
+pub struct Exporter {
+    file: Option<LineWriter<File>>,
+    format: ExportFormat,
+    columns: Vec<String>,
+}
+
+impl Exporter {
+    pub fn new(path: Option<&str>, format: ExportFormat) -> io::Result<Self> {
+        let columns: Vec<String> = ["timestamp", "tick", "pid", "cgroup", "status", "children"]
+            .into_iter()
+            .map(String::from)
+            .chain(REGISTRY.iter().map(column))
+            .chain(EXTRA.iter().map(|(name, _)| name.to_string()))
+            .collect();
+        let Some(path) = path else {
+            return Ok(Exporter {
+                file: None,
+                format,
+                columns,
+            });
+        };
+
+        let mut file = LineWriter::new(File::create(path)?);
+        if format == ExportFormat::Csv {
+            writeln!(file, "{}", columns.join(","))?;
+        }
+        Ok(Exporter {
+            file: Some(file),
+            format,
+            columns,
+        })
+    }
+
+    // `cgroup` identifies cgroup targets, whose processes come and go
+    pub fn export(
+        &mut self,
+        tick: usize,
+        pid: Pid,
+        cgroup: Option<&Path>,
+        process_data: &ProcessData,
+    ) -> io::Result<()> {
+        let Some(ref mut file) = self.file else {
+            return Ok(());
+        };
+
+        let timestamp = Local::now().to_rfc3339();
+        let cgroup = cgroup
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        let values: Vec<Value> = [
+            Value::Text(&timestamp),
+            Value::Count(tick as u64),
+            Value::Count(pid.as_u32() as u64),
+            Value::Text(&cgroup),
+            Value::Text(&process_data.status),
+            Value::Count(process_data.children as u64),
+        ]
+        .into_iter()
+        .chain(REGISTRY.iter().map(|spec| value(spec, process_data)))
+        .chain(EXTRA.iter().map(|(_, value)| value(process_data)))
+        .collect();
+
+        match self.format {
+            ExportFormat::Csv => {
+                let values: Vec<String> = values.iter().map(Value::csv).collect();
+                writeln!(file, "{}", values.join(","))
+            }
+            ExportFormat::Jsonl => {
+                // Поля пишем сами, чтобы сохранить порядок колонок
+                let mut fields = Vec::with_capacity(values.len());
+                for (column, value) in self.columns.iter().zip(&values) {
+                    fields.push(format!(
+                        "{}:{}",
+                        serde_json::to_string(column)?,
+                        value.json()?
+                    ));
+                }
+                writeln!(file, "{{{}}}", fields.join(","))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_come_from_the_registry() {
+        let exporter = Exporter::new(None, ExportFormat::Csv).unwrap();
+        let expected = [
+            "cpu",
+            "rss_mb",
+            "disk_write_mb_s",
+            "disk_read_mb_s",
+            "net_rx_mb_s",
+            "net_tx_mb_s",
+            "sockets",
+            "vmem_mb",
+            "swap_mb",
+            "pss_mb",
+            "uss_mb",
+            "threads",
+            "fds",
+            "ctx_voluntary_s",
+            "ctx_involuntary_s",
+            "minor_faults_s",
+            "major_faults_s",
+            "user_cpu",
+            "system_cpu",
+        ];
+        assert_eq!(&exporter.columns[6..6 + REGISTRY.len()], &expected);
+        // Имена колонок не повторяются
+        for (index, column) in exporter.columns.iter().enumerate() {
+            assert!(
+                !exporter.columns[index + 1..].contains(column),
+                "{}",
+                column
+            );
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use clap::ValueEnum;
+use std::collections::VecDeque;
+
+// Number of buckets of one level merged into one bucket of the next level
+const ROLLUP: usize = 10;
+// Rollup levels after the raw samples: 10x and 100x coarser
+const LEVELS: usize = 2;
+
+// Value of a rollup bucket drawn on the chart
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Downsample {
+    // Highest sample of the bucket, spikes stay visible
+    Max,
+    Avg,
+    Min,
+}
+
+// Samples merged into one bucket, times in seconds since the first sample
+#[derive(Clone, Copy, Debug)]
+struct Rollup {
+    first: f64,
+    last: f64,
+    min: f32,
+    max: f32,
+    sum: f32,
+    count: usize,
+    // Buckets of the previous level merged into this one
+    parts: usize,
+}
+
+impl Rollup {
+    fn merge(&mut self, other: &Rollup) {
+        self.last = other.last;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+        self.count += other.count;
+        self.parts += 1;
+    }
+
+    fn value(&self, downsample: Downsample) -> f32 {
+        match downsample {
+            Downsample::Max => self.max,
+            Downsample::Avg => self.sum / self.count.max(1) as f32,
+            Downsample::Min => self.min,
+        }
+    }
+}
+
+// Multi-resolution history of one series: the latest `capacity` raw samples
+// and, for older data, up to `capacity` min/max/avg buckets per rollup level
+#[derive(Clone, Debug)]
+pub struct History {
+    capacity: usize,
+    // (time, value) of the raw samples, newest last
+    raw: VecDeque<(f64, f32)>,
+    // Complete buckets of every level and the one still being filled
+    levels: Vec<VecDeque<Rollup>>,
+    pending: Vec<Option<Rollup>>,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        History {
+            capacity: capacity.max(1),
+            raw: VecDeque::new(),
+            levels: vec![VecDeque::new(); LEVELS],
+            pending: vec![None; LEVELS],
+        }
+    }
+
+    // Most points `points` returns for the given capacity
+    pub fn max_points(capacity: usize) -> usize {
+        capacity.max(1) * (LEVELS + 1) + LEVELS
+    }
+
+    pub fn push(&mut self, time: f64, value: f32) {
+        self.raw.push_back((time, value));
+        if self.raw.len() <= self.capacity {
+            return;
+        }
+        let Some((time, value)) = self.raw.pop_front() else {
+            return;
+        };
+        self.roll_up(
+            0,
+            Rollup {
+                first: time,
+                last: time,
+                min: value,
+                max: value,
+                sum: value,
+                count: 1,
+                parts: 1,
+            },
+        );
+    }
+
+    // Adds a bucket that fell out of the previous level; the oldest buckets of
+    // the last level are dropped
+    fn roll_up(&mut self, level: usize, bucket: Rollup) {
+        if level >= LEVELS {
+            return;
+        }
+        let pending = match self.pending[level] {
+            Some(ref mut pending) => {
+                pending.merge(&bucket);
+                *pending
+            }
+            None => Rollup { parts: 1, ..bucket },
+        };
+        if pending.parts < ROLLUP {
+            self.pending[level] = Some(pending);
+            return;
+        }
+        self.pending[level] = None;
+        self.levels[level].push_back(pending);
+        if self.levels[level].len() > self.capacity {
+            if let Some(oldest) = self.levels[level].pop_front() {
+                self.roll_up(level + 1, oldest);
+            }
+        }
+    }
+
+    // (age in seconds, value) of all points, oldest first. Buckets are placed
+    // in the middle of the time they cover
+    pub fn points(&self, downsample: Downsample) -> Vec<(f32, f32)> {
+        let now = self.raw.back().map_or(0., |&(time, _)| time);
+        let mut points = Vec::new();
+        for level in (0..LEVELS).rev() {
+            for bucket in self.levels[level].iter().chain(&self.pending[level]) {
+                let time = (bucket.first + bucket.last) / 2.;
+                points.push(((now - time) as f32, bucket.value(downsample)));
+            }
+        }
+        for &(time, value) in &self.raw {
+            points.push(((now - time) as f32, value));
+        }
+        points
+    }
+
+    // Highest raw sample, 0 if there is none
+    pub fn recent_max(&self) -> f32 {
+        self.raw.iter().fold(0., |max, &(_, value)| max.max(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // History with one sample per second, valued like its time
+    fn history(capacity: usize, samples: usize) -> History {
+        let mut history = History::new(capacity);
+        for second in 0..samples {
+            history.push(second as f64, second as f32);
+        }
+        history
+    }
+
+    #[test]
+    fn keeps_raw_samples_up_to_capacity() {
+        let history = history(10, 10);
+        let expected: Vec<(f32, f32)> = (0..10).map(|i| ((9 - i) as f32, i as f32)).collect();
+        assert_eq!(history.points(Downsample::Max), expected);
+        assert_eq!(history.recent_max(), 9.);
+    }
+
+    #[test]
+    fn rolls_up_ten_samples_into_a_bucket() {
+        // Одиннадцатый сэмпл начинает ещё неполную корзину
+        let points = history(10, 11).points(Downsample::Max);
+        assert_eq!(points.len(), 11);
+        assert_eq!(points[0], (10., 0.));
+
+        // Двадцать сэмплов: полная корзина 0..=9 и 10 сырых
+        let history = history(10, 20);
+        for (downsample, value) in [
+            (Downsample::Max, 9.),
+            (Downsample::Avg, 4.5),
+            (Downsample::Min, 0.),
+        ] {
+            let points = history.points(downsample);
+            assert_eq!(points.len(), 11);
+            // Корзина стоит в середине своего времени: 19 - 4.5
+            assert_eq!(points[0], (14.5, value));
+            assert_eq!(points[1], (9., 10.));
+        }
+    }
+
+    #[test]
+    fn rolls_up_hundred_samples_into_the_next_level() {
+        // Сэмплы 0..=99 уходят на второй уровень, когда заполнена корзина 100..=109
+        let history = history(1, 111);
+        assert_eq!(
+            history.points(Downsample::Max),
+            vec![(60.5, 99.), (5.5, 109.), (0., 110.)]
+        );
+        assert_eq!(
+            history.points(Downsample::Avg),
+            vec![(60.5, 49.5), (5.5, 104.5), (0., 110.)]
+        );
+        assert_eq!(
+            history.points(Downsample::Min),
+            vec![(60.5, 0.), (5.5, 100.), (0., 110.)]
+        );
+        assert_eq!(history.recent_max(), 110.);
+    }
+
+    #[test]
+    fn points_are_oldest_first_and_bounded() {
+        for capacity in [1, 3, 10] {
+            let history = history(capacity, 5000);
+            let points = history.points(Downsample::Avg);
+            assert!(points.len() <= History::max_points(capacity));
+            assert!(points.windows(2).all(|pair| pair[0].0 > pair[1].0));
+            assert_eq!(points.last(), Some(&(0., 4999.)));
+        }
+    }
+
+    #[test]
+    fn empty_history() {
+        let history = History::new(0);
+        assert!(history.points(Downsample::Max).is_empty());
+        assert_eq!(history.recent_max(), 0.);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::Args;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use monit_tool::history::History;
+use monit_tool::series::{self, REGISTRY};
+use std::time::{Duration, Instant};
+
+// Smallest number of samples the time window can be zoomed in to
+const MIN_WINDOW: usize = 20;
+// Smallest number of seconds the `--last` window can be zoomed in to
+const MIN_SPAN: f32 = 10.;
+
+pub enum Action {
+    Quit,
+    Pause,
+    // Shows or hides the series with this name
+    Toggle(&'static str),
+    ZoomIn,
+    ZoomOut,
+    Restart,
+    Kill,
+    Switch,
+    // Terminal was resized, the layout has to be redrawn
+    Resize,
+}
+
+// What the chart currently shows, changed from the keyboard
+pub struct View {
+    pub paused: bool,
+    // Whether each series of `REGISTRY` is drawn, in the same order
+    pub enabled: Vec<bool>,
+    // Number of latest points shown on the chart and the most there can be
+    pub window: usize,
+    pub max_window: usize,
+    // Seconds shown on the chart instead, set with `--last`
+    pub span: Option<f32>,
+}
+
+impl View {
+    pub fn new(args: &Args) -> Self {
+        let metrics = args.metrics();
+        View {
+            paused: false,
+            enabled: REGISTRY
+                .iter()
+                .map(|spec| metrics.iter().any(|metric| metric.name == spec.name))
+                .collect(),
+            window: History::max_points(args.history),
+            max_window: History::max_points(args.history),
+            span: args.last.map(|last| last.as_secs_f32()),
+        }
+    }
+
+    // Applies view-only actions, the other ones are handled by the caller
+    pub fn apply(&mut self, action: &Action) {
+        match action {
+            Action::Pause => self.paused = !self.paused,
+            Action::Toggle(name) => {
+                if let Some(index) = series::index(name) {
+                    self.enabled[index] = !self.enabled[index];
+                }
+            }
+            Action::ZoomIn => match self.span {
+                Some(span) => self.span = Some((span / 2.).max(MIN_SPAN)),
+                None => self.window = (self.window / 2).max(MIN_WINDOW),
+            },
+            Action::ZoomOut => match self.span {
+                Some(span) => self.span = Some(span * 2.),
+                None => self.window = (self.window * 2).min(self.max_window),
+            },
+            _ => {}
+        }
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        series::index(name).is_some_and(|index| self.enabled[index])
+    }
+
+    pub fn status_line(&self) -> String {
+        let flag = |name: &str| if self.is_enabled(name) { "on" } else { "off" };
+        let window = match self.span {
+            Some(span) => format!("{}s", span),
+            None => self.window.to_string(),
+        };
+        format!(
+            "[p]ause{} [c]pu:{} [m]em:{} [r]ead:{} [w]rite:{} [+/-] window:{} [R]estart [k]ill [s]witch [q]uit",
+            if self.paused { " (PAUSED)" } else { "" },
+            flag("cpu"),
+            flag("rss"),
+            flag("disk-read"),
+            flag("disk-write"),
+            window
+        )
+    }
+}
+
+// Reads single key presses without waiting for Enter. Only echo and line
+// buffering are turned off, so output and Ctrl+C keep working as usual.
+//
+// crossterm's raw mode is not used on unix: it also clears ISIG, so Ctrl+C
+// would only be read as a key press between samples instead of reaching the
+// ctrlc handler right away, and OPOST, so every `println!` printed while the
+// chart runs would need an explicit `\r`. Other platforms have no termios and use it.
+pub struct Keyboard {
+    enabled: bool,
+    #[cfg(unix)]
+    saved: Option<libc::termios>,
+}
+
+impl Keyboard {
+    pub fn new(enabled: bool) -> Self {
+        let mut keyboard = Keyboard {
+            enabled,
+            #[cfg(unix)]
+            saved: None,
+        };
+        keyboard.resume();
+        keyboard
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    #[cfg(unix)]
+    pub fn resume(&mut self) {
+        if !self.enabled || self.saved.is_some() {
+            return;
+        }
+        // SAFETY: termios is plain data and is only passed to tcgetattr/tcsetattr for stdin
+        unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                self.enabled = false;
+                return;
+            }
+            self.saved = Some(termios);
+            termios.c_lflag &= !(libc::ICANON | libc::ECHO);
+            termios.c_cc[libc::VMIN] = 1;
+            termios.c_cc[libc::VTIME] = 0;
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+        }
+    }
+
+    #[cfg(unix)]
+    pub fn suspend(&mut self) {
+        if let Some(termios) = self.saved.take() {
+            // SAFETY: restores the attributes read in `resume`
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn resume(&mut self) {
+        if self.enabled && crossterm::terminal::enable_raw_mode().is_err() {
+            self.enabled = false;
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn suspend(&mut self) {
+        if self.enabled {
+            let _ = crossterm::terminal::disable_raw_mode();
+        }
+    }
+
+    // Waits up to `timeout` for a key press; used instead of a plain sleep
+    pub fn wait(&self, timeout: Duration) -> Option<Action> {
+        if !self.enabled {
+            std::thread::sleep(timeout);
+            return None;
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if !event::poll(remaining).unwrap_or(false) {
+                return None;
+            }
+            match event::read() {
+                Ok(Event::Key(key)) => {
+                    if let Some(action) = Self::action(key) {
+                        return Some(action);
+                    }
+                }
+                Ok(Event::Resize(..)) => return Some(Action::Resize),
+                _ => {}
+            }
+            if remaining.is_zero() {
+                return None;
+            }
+        }
+    }
+
+    fn action(key: KeyEvent) -> Option<Action> {
+        if key.kind != KeyEventKind::Press {
+            return None;
+        }
+        let action = match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Action::Quit,
+            KeyCode::Char('q') | KeyCode::Esc => Action::Quit,
+            KeyCode::Char('p') | KeyCode::Char(' ') => Action::Pause,
+            KeyCode::Char('c') => Action::Toggle("cpu"),
+            KeyCode::Char('m') => Action::Toggle("rss"),
+            KeyCode::Char('r') => Action::Toggle("disk-read"),
+            KeyCode::Char('w') => Action::Toggle("disk-write"),
+            KeyCode::Char('+') | KeyCode::Char('=') => Action::ZoomIn,
+            KeyCode::Char('-') => Action::ZoomOut,
+            KeyCode::Char('R') => Action::Restart,
+            KeyCode::Char('k') => Action::Kill,
+            KeyCode::Char('s') => Action::Switch,
+            _ => return None,
+        };
+        Some(action)
+    }
+}
+
+impl Drop for Keyboard {
+    fn drop(&mut self) {
+        self.suspend();
+    }
+}
@@ -0,0 +1,50 @@
+// Process tree sampling behind `monit-tool`: collectors for CPU, memory, disk,
+// network and /proc details, the histories the charts are drawn from and the
+// listing of running processes. `Sampler` is the entry point for embedding the
+// sampling elsewhere
+pub mod data_collector;
+pub mod history;
+pub mod process_manager;
+pub mod sampler;
+
+// Building blocks of the `monit-tool` binary. They are only public so the
+// binary can use them and are not part of the library API
+#[doc(hidden)]
+pub mod alert;
+#[doc(hidden)]
+pub mod cgroup;
+#[doc(hidden)]
+pub mod colors;
+#[doc(hidden)]
+pub mod details;
+#[doc(hidden)]
+pub mod net;
+#[doc(hidden)]
+pub mod process_group;
+#[doc(hidden)]
+pub mod selector;
+#[doc(hidden)]
+pub mod series;
+#[doc(hidden)]
+pub mod stats;
+#[doc(hidden)]
+pub mod units;
+
+pub use crate::data_collector::{DataCollector, ProcessData};
+pub use crate::history::History;
+pub use crate::process_manager::{ProcessItem, ProcessManager};
+pub use crate::sampler::Sampler;
+
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, RefreshKind, System};
+
+// Like `refresh_all`, but also drops processes that exited, otherwise their
+// last values keep being summed into the tree
+#[doc(hidden)]
+pub fn refresh_system(system: &mut System) {
+    system.refresh_specifics(RefreshKind::everything().without_processes());
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::everything(),
+    );
+}
@@ -1,34 +1,62 @@
+mod bench;
+mod budget;
 mod chart_manager;
-mod data_collector;
+mod exporter;
+mod keyboard;
 mod logger;
-mod process_manager;
-
-use crate::chart_manager::ChartManager;
-use crate::data_collector::DataCollector;
+mod output_capture;
+mod panels;
+mod recorder;
+mod replay;
+mod restart_policy;
+mod summary;
+mod target;
+
+use crate::budget::{Budgets, BUDGET_EXIT_CODE};
+use crate::chart_manager::{ChartManager, ChartSeries, Layout, TimeAxis};
+use crate::exporter::{ExportFormat, Exporter};
+use crate::keyboard::{Action, Keyboard, View};
 use crate::logger::Logger;
-use crate::process_manager::ProcessManager;
-use clap::Parser;
-use ctrlc;
+use crate::output_capture::OutputCapture;
+use crate::recorder::Recorder;
+use crate::restart_policy::{CrashLoop, RestartMode, RestartPolicy};
+use crate::summary::Summary;
+use crate::target::{ChildState, Target};
+use chrono::Local;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand};
+use monit_tool::alert::{Alert, AlertRule, Metric};
+use monit_tool::colors::{CYAN, GREEN, MAGENTA, PINK, RED, TEAL, WHITE, YELLOW};
+use monit_tool::data_collector::{CpuMode, ProcessData, DEFAULT_HISTORY};
+use monit_tool::history::Downsample;
+use monit_tool::selector::Selector;
+use monit_tool::series::{SeriesSpec, REGISTRY};
+use monit_tool::units::{parse_duration, parse_percent, parse_signal, parse_size};
+use monit_tool::{alert, cgroup, process_group, refresh_system, series, ProcessManager};
 use std::io::{self};
+use std::path::PathBuf;
 use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 use std::{thread, time::Duration};
-use sysinfo::Pid;
-
-const RED: rgb::RGB8 = rgb::RGB8::new(0xFF, 0x00, 0x00);
-const GREEN: rgb::RGB8 = rgb::RGB8::new(0x00, 0xFF, 0x00);
-const PURPLE: rgb::RGB8 = rgb::RGB8::new(0xE0, 0x80, 0xFF);
-const BLUE: rgb::RGB8 = rgb::RGB8::new(0x00, 0x00, 0xFF);
-const ORANGE: rgb::RGB8 = rgb::RGB8::new(0xFF, 0xA5, 0x00);
-
-const PRINT_LEN: usize = 500;
-
-struct ProcessItem {
-    pid: u32,
-    name: String,
-}
+use sysinfo::{Pid, Signal, System};
+
+// Colors for the lines of individual child processes
+const CHILD_COLORS: [(&str, rgb::RGB8); 6] = [
+    ("CYAN", CYAN),
+    ("YELLOW", YELLOW),
+    ("MAGENTA", MAGENTA),
+    ("WHITE", WHITE),
+    ("TEAL", TEAL),
+    ("PINK", PINK),
+];
+
+// Terminal columns taken by the Y-axis labels right of the chart
+const Y_LABEL_COLUMNS: usize = 16;
+// Smallest chart size in dots when it is fitted to the terminal
+const MIN_CHART_WIDTH: usize = 32;
+const MIN_CHART_HEIGHT: usize = 12;
 
 fn format_duration(duration: Duration) -> String {
     let total_seconds = duration.as_secs();
@@ -46,32 +74,78 @@ fn start_process(command: &str, workdir: &str) -> Result<Child, io::Error> {
             .arg("/c")
             .arg(command)
             .current_dir(workdir)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
             .spawn()?
     } else {
-        // Для Unix-подобных систем используем sh с параметром -c
-        Command::new("sh")
-            .arg("-c")
-            .arg(command)
-            .current_dir(workdir)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()?
+        // Для Unix-подобных систем используем sh с параметром -c в своей группе процессов.
+        // Из фоновой группы чтение терминала остановило бы команду (SIGTTIN), а клавиши
+        // читает сам monit-tool, поэтому stdin пустой
+        process_group::isolate(
+            Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .current_dir(workdir)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped()),
+        )
+        .spawn()?
     };
 
     Ok(child)
 }
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Replay a session recorded with --record
+    Replay {
+        /// Recording file
+        file: String,
+
+        /// Playback speed multiplier
+        #[arg(long, default_value_t = 1.0)]
+        speed: f32,
+    },
+    /// Run a command several times and report its resource use; given twice, compare B against A
+    Bench {
+        /// Command to run (give it twice for an A/B comparison)
+        #[arg(short, long, required = true)]
+        command: Vec<String>,
+
+        /// Number of runs of every command
+        #[arg(long, default_value_t = 5, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        runs: usize,
+    },
+}
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Name of the process
-    #[arg(short, long, default_value_t = String::from(""))]
-    name: String,
+    #[command(subcommand)]
+    subcommand: Option<Commands>,
+
+    /// Name of the process (can be repeated)
+    #[arg(short, long)]
+    name: Vec<String>,
 
-    /// Command to execute and monitor
-    #[arg(short, long, default_value_t = String::from(""))]
-    command: String,
+    /// Monitor the process whose PID is in this file, re-read on restart (can be repeated)
+    #[arg(long)]
+    pidfile: Vec<PathBuf>,
+
+    /// Monitor the process running this executable (can be repeated)
+    #[arg(long)]
+    exe: Vec<PathBuf>,
+
+    /// Monitor all processes of a cgroup v2 directory together, absolute or
+    /// relative to /sys/fs/cgroup, e.g. system.slice/nginx.service (can be repeated)
+    #[arg(long)]
+    cgroup: Vec<String>,
+
+    /// Command to execute and monitor (can be repeated)
+    #[arg(short, long)]
+    command: Vec<String>,
 
     /// Working directory for the command
     #[arg(long, default_value_t = String::from("."))]
@@ -81,56 +155,460 @@ struct Args {
     #[arg(short, long, default_value_t = false)]
     watch: bool,
 
+    /// Which exits --watch restarts a command after
+    #[arg(long, value_enum, default_value_t = RestartMode::Always)]
+    restart: RestartMode,
+
+    /// Stop restarting a command after this many restarts
+    #[arg(long)]
+    max_restarts: Option<u32>,
+
+    /// Delay before restarting a command, doubled (with jitter) after every further restart
+    #[arg(long, value_parser = parse_duration, default_value = "500ms")]
+    backoff: Duration,
+
+    /// Upper limit of the restart delay
+    #[arg(long, value_parser = parse_duration, default_value = "30s")]
+    max_backoff: Duration,
+
+    /// Stop restarting a command after N restarts within a time window, e.g. 5/60s
+    #[arg(long, value_name = "N/WINDOW")]
+    crash_loop: Option<CrashLoop>,
+
     /// Enable logging to file
     #[arg(short, long, default_value_t = false)]
     logging: bool,
 
     /// Enable disk write info
-    #[arg(long, default_value_t = false)]
+    #[arg(long, global = true, default_value_t = false)]
     disk_write: bool,
 
     /// Enable disk read info
-    #[arg(long, default_value_t = false)]
+    #[arg(long, global = true, default_value_t = false)]
     disk_read: bool,
 
+    /// Enable network receive info of the tree's network namespaces
+    #[arg(long, global = true, default_value_t = false)]
+    net_rx: bool,
+
+    /// Enable network send info of the tree's network namespaces
+    #[arg(long, global = true, default_value_t = false)]
+    net_tx: bool,
+
+    /// Enable socket count info (TCP sockets by state in the label)
+    #[arg(long, global = true, default_value_t = false)]
+    net_sockets: bool,
+
+    /// Series drawn on the main chart, comma-separated, e.g. cpu,rss,fds (default: cpu,rss)
+    #[arg(long, global = true, value_delimiter = ',', value_parser = series::parse)]
+    metrics: Vec<&'static SeriesSpec>,
+
     /// Disable chart output
     #[arg(long, default_value_t = false)]
     nochart: bool,
 
+    /// Write every sample to this file
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Format of the --output file
+    #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+    format: ExportFormat,
+
+    /// How CPU usage is shown: per-core (100% = one core) or normalized (100% = all cores)
+    #[arg(long, value_enum, default_value_t = CpuMode::PerCore)]
+    cpu_mode: CpuMode,
+
+    /// Show a per-core heatmap of the CPU used by the threads of the tree
+    #[arg(long, default_value_t = false)]
+    heatmap: bool,
+
+    /// How the series share the Y-axis: overlay, stacked (one sub-chart per series) or normalized
+    #[arg(long, value_enum, default_value_t = Layout::Overlay)]
+    layout: Layout,
+
+    /// Number of raw samples kept per series; older ones are rolled up into
+    /// 10x and 100x coarser min/max/avg buckets
+    #[arg(long, default_value_t = DEFAULT_HISTORY, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(10..))]
+    history: usize,
+
+    /// Value of the rolled-up buckets drawn on the chart
+    #[arg(long, value_enum, default_value_t = Downsample::Max)]
+    downsample: Downsample,
+
+    /// Labels of the time axis: wall-clock time or time since the start
+    #[arg(long, value_enum, default_value_t = TimeAxis::Wall)]
+    time_axis: TimeAxis,
+
+    /// Show only the last period on the chart, e.g. 30s, 5m (default: the whole history)
+    #[arg(long, value_parser = parse_duration)]
+    last: Option<Duration>,
+
+    /// Chart width in braille dots, 2 per terminal column (default: fit the terminal)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(32..))]
+    width: Option<u32>,
+
+    /// Chart height in braille dots, 4 per terminal row (default: fit the terminal)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(3..))]
+    height: Option<u32>,
+
+    /// Use a logarithmic Y-axis
+    #[arg(long, default_value_t = false)]
+    log_scale: bool,
+
+    /// Show the host CPU per core, memory, swap and load average above the chart
+    #[arg(long, default_value_t = false)]
+    host: bool,
+
+    /// Show the process tree with per-child values under the chart
+    #[arg(long, default_value_t = false)]
+    tree: bool,
+
+    /// Number of processes shown in the tree panel
+    #[arg(long, default_value_t = 10)]
+    tree_rows: usize,
+
+    /// Chart the memory of the N children with the highest usage individually (max 6)
+    #[arg(long, default_value_t = 0)]
+    top_children: usize,
+
+    /// Chart a series in an extra panel, e.g. fds or threads (can be repeated)
+    #[arg(long, value_parser = series::parse)]
+    chart: Vec<&'static SeriesSpec>,
+
+    /// Number of lines of the command output shown under the chart
+    #[arg(long, default_value_t = 5)]
+    tail: usize,
+
+    /// Tee the command output to <PREFIX>.stdout.log and <PREFIX>.stderr.log
+    #[arg(long, value_name = "PREFIX")]
+    tee: Option<String>,
+
+    /// Mark the timeline when a line of the command output contains this text (can be repeated)
+    #[arg(long)]
+    mark: Vec<String>,
+
+    /// Alert rule, e.g. 'mem>500MB for 10s' or 'cpu>90% for 30s then exit:3' (can be repeated)
+    #[arg(long)]
+    alert: Vec<AlertRule>,
+
+    /// Fail if the peak memory of a process exceeds this size, e.g. 500MB
+    #[arg(long, value_parser = parse_size)]
+    max_memory: Option<f32>,
+
+    /// Fail if the average CPU usage of a process exceeds this percentage
+    #[arg(long, value_parser = parse_percent)]
+    max_avg_cpu: Option<f32>,
+
+    /// Fail (and stop monitoring) if the session runs longer than this, e.g. 10m
+    #[arg(long, value_parser = parse_duration)]
+    max_runtime: Option<Duration>,
+
+    /// Fail if the total disk write of a process exceeds this size, e.g. 1GB
+    #[arg(long, value_parser = parse_size)]
+    max_disk_write: Option<f32>,
+
+    /// Exit with the exit code of the monitored command if it failed
+    #[arg(long, default_value_t = false)]
+    propagate_exit_code: bool,
+
+    /// Signal sent to the process group of a command when it is stopped
+    #[arg(long, value_parser = parse_signal, default_value = "TERM")]
+    stop_signal: Signal,
+
+    /// Time the command gets to exit after --stop-signal before it is killed
+    #[arg(long, value_parser = parse_duration, default_value = "5s")]
+    grace_period: Duration,
+
+    /// Record the session to this file for a later replay
+    #[arg(long)]
+    record: Option<String>,
+
     /// timeout before refresh
     #[arg(long, default_value_t = 50)]
     sleep: u64,
 }
 
-fn x_label_format(
-    tick: usize,
-    cpu_usage: f32,
-    memory_usage: f32,
-    total_written_bytes: f32,
-    total_read_bytes: f32,
-    name: String,
-    status: String,
-    disk_write: bool,
-    disk_read: bool,
-) -> String {
-    let mut label = format!(
-        "{} ORANGE = CPU (Usage: {:.2} %), GREEN/RED = Memory (Usage: {:.2} MB)",
-        tick, cpu_usage, memory_usage
-    );
-    if disk_write {
-        label += &format!(
-            ", PURPLE - disk write (Usage: {:.2} MB)",
-            total_written_bytes
-        );
-    }
-    if disk_read {
-        label += &format!(", BLUE - disk read (Usage: {:.2} MB)", total_read_bytes);
+impl Args {
+    // Series of the main chart: `--metrics` plus the older per-series flags
+    fn metrics(&self) -> Vec<&'static SeriesSpec> {
+        let mut metrics = self.metrics.clone();
+        if metrics.is_empty() {
+            metrics = vec![&REGISTRY[0], &REGISTRY[1]];
+        }
+        for (enabled, name) in [
+            (self.disk_write, "disk-write"),
+            (self.disk_read, "disk-read"),
+            (self.net_rx, "net-rx"),
+            (self.net_tx, "net-tx"),
+            (self.net_sockets, "sockets"),
+        ] {
+            if enabled && !metrics.iter().any(|spec| spec.name == name) {
+                metrics.extend(series::parse(name));
+            }
+        }
+        metrics
     }
-    label += &format!(" - {}", name);
-    label += &format!(" ({})", status);
+}
+
+fn x_label_format(time: &str, id: &str, process_data: &ProcessData, view: &View) -> String {
+    let legend: Vec<String> = REGISTRY
+        .iter()
+        .zip(&view.enabled)
+        .filter(|&(_, &enabled)| enabled)
+        .map(|(spec, _)| spec.legend(process_data))
+        .collect();
+    let mut label = format!("{} {}", time, legend.join(", "));
+    label += &format!(" - [{}] {}", id, process_data.name);
+    label += &format!(" ({})", process_data.status);
     label
 }
 
+fn print_summaries(targets: &[Target], watch: bool, elapsed_time: Duration) -> Vec<Summary> {
+    println!("\nProgram finished.");
+    let summaries: Vec<Summary> = targets.iter().map(Summary::from_target).collect();
+    for summary in &summaries {
+        summary.print(watch);
+    }
+    println!("\nTotal runtime: {}", format_duration(elapsed_time));
+    summaries
+}
+
+fn draw_targets(
+    term: &console::Term,
+    chart_manager: &mut ChartManager,
+    targets: &[Target],
+    args: &Args,
+    view: &View,
+    logger: &mut Logger,
+    host: Option<&System>,
+) -> Result<(), io::Error> {
+    let (width, height) = chart_size(args, targets, view, host);
+    if (width, height) != (chart_manager.width, chart_manager.height) {
+        // Размер терминала изменился - старый кадр стираем целиком
+        term.clear_screen().unwrap();
+        chart_manager.set_size(width, height);
+    }
+    term.move_cursor_to(0, 0).unwrap();
+
+    if let Some(system) = host {
+        let shares: Vec<(Pid, &ProcessData)> = targets
+            .iter()
+            .filter_map(|target| Some((target.pid, target.last.as_ref()?)))
+            .collect();
+        panels::draw_host(system, &shares);
+    }
+
+    for target in targets {
+        let Some(ref process_data) = target.last else {
+            continue;
+        };
+        let data_collector = &target.data_collector;
+        let last_sample = data_collector.last_sample().unwrap_or_else(Local::now);
+        let first_sample = data_collector.first_sample().unwrap_or(last_sample);
+        let time = args.time_axis.format(last_sample, first_sample);
+        let mut x_label = x_label_format(&time, &target.label(), process_data, view);
+        logger.log(&x_label)?;
+
+        // Подсвечиваем сработавшие правила
+        let active_alerts: Vec<String> = target
+            .alerts
+            .iter()
+            .filter(|alert| alert.active)
+            .map(|alert| alert.rule.to_string())
+            .collect();
+        if !active_alerts.is_empty() {
+            x_label = format!("[ALERT: {}] {}", active_alerts.join("; "), x_label);
+        }
+        let series: Vec<ChartSeries> = REGISTRY
+            .iter()
+            .zip(&view.enabled)
+            .filter(|&(_, &enabled)| enabled)
+            .filter_map(|(spec, _)| Some((spec, data_collector.series(spec.name)?)))
+            .map(|(spec, history)| {
+                let color = if spec.alert.is_some_and(|metric| target.alert_active(metric)) {
+                    RED
+                } else if spec.alert == Some(Metric::Memory) {
+                    target.memory_color
+                } else {
+                    spec.color.1
+                };
+                ChartSeries {
+                    name: spec.label,
+                    data: history.points(args.downsample),
+                    color,
+                    unit: spec.unit,
+                }
+            })
+            .collect();
+
+        chart_manager
+            .set_series(series)
+            .set_x_label(x_label)
+            .set_cpu_usage(process_data.cpu_usage)
+            .set_memory_usage(process_data.memory_usage)
+            .set_window(view.window)
+            .set_span(view.span)
+            .set_time_axis(args.time_axis)
+            .set_sample_times(first_sample, last_sample)
+            .set_layout(args.layout)
+            .set_log_scale(args.log_scale)
+            .set_marks(target.mark_positions())
+            .set_max(target.max)
+            .draw_chart();
+
+        if let Some(ref output) = target.output {
+            if args.tail > 0 {
+                panels::draw_output(&output.last_lines(args.tail), args.tail);
+            }
+        }
+
+        if args.top_children > 0 {
+            let top_children = target.top_children(args.top_children.min(CHILD_COLORS.len()));
+            let mut label = String::from("Top children by memory:");
+            let mut children_data = Vec::new();
+            for (child, (color_name, color)) in top_children.iter().zip(CHILD_COLORS) {
+                label += &format!(
+                    " {} = [{}] {} ({:.2} MB, {:.2} %)",
+                    color_name, child.pid, child.name, child.memory_usage, child.cpu_usage
+                );
+                children_data.push((child.memory_data.points(args.downsample), color));
+            }
+            chart_manager
+                .set_children_data(children_data)
+                .set_children_label(label)
+                .draw_children_chart();
+        }
+
+        if !args.chart.is_empty() {
+            let mut labels = Vec::new();
+            let mut details_data = Vec::new();
+            for (spec, (color_name, color)) in args.chart.iter().zip(CHILD_COLORS) {
+                let Some(history) = data_collector.series(spec.name) else {
+                    continue;
+                };
+                labels.push(format!(
+                    "{} = {}: {}",
+                    color_name,
+                    spec.label,
+                    spec.format((spec.value)(process_data))
+                ));
+                details_data.push((history.points(args.downsample), color));
+            }
+            chart_manager
+                .set_details_data(details_data)
+                .set_details_label(labels.join(", "))
+                .draw_details_chart();
+        }
+
+        if args.heatmap {
+            panels::draw_heatmap(data_collector.core_data(), view.window);
+        }
+
+        if args.tree {
+            panels::draw_tree(&process_data.tree, args.tree_rows);
+        }
+    }
+
+    Ok(())
+}
+
+// Chart size in braille dots: `--width`/`--height` or what fits the terminal
+// next to the panels, with the rows shared between the charts of all targets.
+// The stacked layout draws one chart per enabled series instead of one
+fn chart_size(args: &Args, targets: &[Target], view: &View, host: Option<&System>) -> (u32, u32) {
+    let (rows, columns) = console::Term::stdout().size();
+    let width = args.width.unwrap_or_else(|| {
+        ((columns as usize).saturating_sub(Y_LABEL_COLUMNS) * 2).max(MIN_CHART_WIDTH) as u32
+    });
+    let height = args.height.unwrap_or_else(|| {
+        let main_charts = match args.layout {
+            Layout::Stacked => view
+                .enabled
+                .iter()
+                .filter(|&&enabled| enabled)
+                .count()
+                .max(1),
+            Layout::Overlay | Layout::Normalized => 1,
+        };
+        let charts =
+            main_charts + usize::from(args.top_children > 0) + usize::from(!args.chart.is_empty());
+        // Строка состояния, строка под ней для курсора, панели и у каждого графика
+        // строка оси X и три строки подписей
+        let mut fixed = 2 + host.map_or(0, |system| panels::host_rows(system, targets.len()));
+        for target in targets {
+            fixed += 4 * charts;
+            if target.output.is_some() {
+                fixed += args.tail;
+            }
+            if args.heatmap {
+                fixed += 1 + target.data_collector.core_data().back().map_or(0, Vec::len);
+            }
+            if args.tree {
+                fixed += args.tree_rows + 2;
+            }
+        }
+        let free = (rows as usize).saturating_sub(fixed) / (targets.len().max(1) * charts);
+        (free * 4).max(MIN_CHART_HEIGHT) as u32
+    });
+    (width, height)
+}
+
+// Lets the user pick processes for every filter and wraps them into targets
+fn pick_targets(process_manager: &ProcessManager, filters: &[String]) -> Vec<Target> {
+    let mut targets = Vec::new();
+    for filter in filters {
+        let processes = process_manager.get_filtered_processes(filter);
+        for selected_index in process_manager.select_processes(&processes) {
+            let pid = Pid::from_u32(processes[selected_index].pid);
+            let mut target = Target::new(pid, None, None);
+            target.selector = process_manager
+                .system
+                .process(pid)
+                .map(|process| Selector::from_process(process, filter));
+            targets.push(target);
+        }
+    }
+    targets
+}
+
+// Applies the per-target options from the command line
+fn configure_targets(targets: &mut [Target], args: &Args) {
+    for target in targets.iter_mut() {
+        target.alerts = args.alert.iter().cloned().map(Alert::new).collect();
+        target.track_children = args.top_children > 0;
+        target.data_collector.net = args
+            .metrics()
+            .iter()
+            .chain(&args.chart)
+            .any(|spec| matches!(spec.name, "net-rx" | "net-tx" | "sockets"));
+        target.data_collector.cpu_mode = args.cpu_mode;
+        // Дорогие /proc данные читаем, только если они показываются или сохраняются
+        let needed = |names: [&str; 2]| {
+            args.output.is_some()
+                || args.record.is_some()
+                || args
+                    .metrics()
+                    .iter()
+                    .chain(&args.chart)
+                    .any(|spec| names.contains(&spec.name))
+        };
+        target.data_collector.smaps = needed(["pss", "uss"]);
+        target.data_collector.ctx_switches = needed(["ctx-voluntary", "ctx-involuntary"]);
+        target.data_collector.heatmap = args.heatmap;
+        target.data_collector.set_history(args.history);
+        target.stop_signal = args.stop_signal;
+        target.grace_period = args.grace_period;
+        target.restart_policy = RestartPolicy {
+            mode: args.restart,
+            max_restarts: args.max_restarts,
+            backoff: args.backoff,
+            max_backoff: args.max_backoff,
+            crash_loop: args.crash_loop,
+        };
+    }
+}
+
 fn main() -> Result<(), io::Error> {
     let args = Args::parse();
 
@@ -144,222 +622,350 @@ fn main() -> Result<(), io::Error> {
     })
     .expect("Error setting Ctrl+C handler");
 
-    let process_manager = ProcessManager::new();
-    let mut child = if !args.command.is_empty() {
-        // Если указана команда, запускаем процесс и получаем его Child
-        Some(start_process(&args.command, &args.workdir)?)
-    } else {
-        None
-    };
+    if let Some(Commands::Replay { ref file, speed }) = args.subcommand {
+        return replay::replay(&args, file, speed, &running);
+    }
+    if let Some(Commands::Bench { ref command, runs }) = args.subcommand {
+        if command.len() > 2 {
+            Args::command()
+                .error(
+                    ErrorKind::TooManyValues,
+                    "bench compares at most two commands (A and B)",
+                )
+                .exit();
+        }
+        return bench::bench(&args, command, runs, &running);
+    }
 
-    let mut pid = if let Some(ref child_process) = child {
-        Pid::from_u32(child_process.id())
-    } else {
-        // Если процесс выбран из списка, используем его PID
-        let processes = process_manager.get_filtered_processes(&args.name);
-        let selected_index = process_manager.select_process(&processes).unwrap();
-        Pid::from_u32(processes[selected_index].pid)
-    };
+    let mut process_manager = ProcessManager::new();
+    let mut targets: Vec<Target> = Vec::new();
+
+    // Задержка между обновлениями нужна для точного CPU usage. Команды запускаем
+    // после неё, иначе короткая команда завершается раньше первого замера
+    refresh_system(&mut process_manager.system);
+    thread::sleep(Duration::from_millis(500));
+    refresh_system(&mut process_manager.system);
+
+    // Если указаны команды, запускаем процессы и получаем их Child
+    for (index, command) in args.command.iter().enumerate() {
+        let tee_prefix = args.tee.as_ref().map(|prefix| {
+            if args.command.len() > 1 {
+                format!("{}-{}", prefix, index + 1)
+            } else {
+                prefix.clone()
+            }
+        });
+        let output = OutputCapture::new(tee_prefix.as_deref(), &args.mark)?;
+        targets.push(Target::spawn(command, &args.workdir, output)?);
+    }
+
+    // Процессы, заданные через pidfile или путь к исполняемому файлу
+    let selectors = args
+        .pidfile
+        .iter()
+        .map(|path| Selector {
+            pidfile: Some(path.clone()),
+            ..Default::default()
+        })
+        .chain(args.exe.iter().map(|path| Selector {
+            exe: Some(path.canonicalize().unwrap_or_else(|_| path.clone())),
+            ..Default::default()
+        }));
+    for selector in selectors {
+        match selector.resolve(&process_manager.system) {
+            Some(pid) => {
+                let mut target = Target::new(pid, None, None);
+                target.selector = Some(selector);
+                targets.push(target);
+            }
+            None => println!("No running process found for {}", selector),
+        }
+    }
+
+    for path in &args.cgroup {
+        let path = cgroup::resolve(path);
+        if !path.join("cgroup.procs").is_file() {
+            println!("Not a cgroup v2 directory: {}", path.display());
+            continue;
+        }
+        let mut target = Target::new(Pid::from_u32(0), None, None);
+        target.cgroup = Some(path);
+        targets.push(target);
+    }
+
+    // Если процессы выбираются из списка, используем их PID
+    let selected = !args.command.is_empty()
+        || !args.pidfile.is_empty()
+        || !args.exe.is_empty()
+        || !args.cgroup.is_empty();
+    if !selected || !args.name.is_empty() {
+        let filters = if args.name.is_empty() {
+            vec![String::new()]
+        } else {
+            args.name.clone()
+        };
+        targets.extend(pick_targets(&process_manager, &filters));
+    }
+
+    if targets.is_empty() {
+        println!("No process selected.");
+        return Ok(());
+    }
+
+    configure_targets(&mut targets, &args);
 
     let term = console::Term::stdout();
     term.hide_cursor().unwrap();
     term.clear_screen().unwrap();
 
-    let mut data_collector = DataCollector::new();
-    let mut restart_count = 0;
-    let mut tick = 0;
-    let mut max: f32 = 0.;
-    let mut memory_usage_min: f32 = 0.;
-    let mut memory_color = GREEN;
     let mut logger = Logger::new(args.logging)?;
+    let mut exporter = Exporter::new(args.output.as_deref(), args.format)?;
+    let mut recorder = Recorder::new(
+        args.record.as_deref(),
+        &args.command,
+        &args.workdir,
+        args.sleep,
+    )?;
     let mut system = process_manager.system;
 
-    system.refresh_all();
-
-    thread::sleep(Duration::from_millis(500)); // Небольшая задержка
-
-    system.refresh_all();
-
     let mut chart_manager = ChartManager::new();
-
-    // Переменные для хранения максимальных значений
-    let mut max_cpu_usage: f32 = 0.;
-    let mut max_memory_usage: f32 = 0.;
+    let mut view = View::new(&args);
+    let mut keyboard = Keyboard::new(!args.nochart && console::user_attended());
+    let mut redraw = true;
 
     // Время начала работы программы
     let start_time = Instant::now();
 
+    // Код выхода, заданный сработавшим правилом
+    let mut exit_code: Option<i32> = None;
+
+    // Targets replaced with [s]witch: their commands keep running and they are
+    // stopped and summarized together with the current ones at the end
+    let mut switched_out: Vec<Target> = Vec::new();
+
+    let budgets = Budgets {
+        max_memory: args.max_memory,
+        max_avg_cpu: args.max_avg_cpu,
+        max_runtime: args.max_runtime,
+        max_disk_write: args.max_disk_write,
+    };
+
     // Основной цикл
     while running.load(Ordering::SeqCst) {
-        // Проверяем состояние дочернего процесса, если он был запущен
-        if let Some(ref mut child_process) = child {
-            if let Ok(Some(status)) = child_process.try_wait() {
-                println!("Process exited with status: {}", status);
-
-                if args.watch && !args.command.is_empty() {
-                    // Перезапускаем процесс, если включен режим watch и есть команда
-                    println!("Restarting process...");
-                    child = Some(start_process(&args.command, &args.workdir)?);
-                    pid = Pid::from_u32(child.as_ref().unwrap().id());
-                    restart_count += 1;
-                    continue; // Пропускаем остальную часть цикла и начинаем заново
-                } else {
-                    break; // Завершаем программу, если процесс завершился и перезапуск не требуется
-                }
+        // Проверяем состояние дочерних процессов, если они были запущены
+        for target in targets.iter_mut().filter(|target| !target.finished) {
+            match target.poll_child(args.watch, &args.workdir)? {
+                ChildState::Running | ChildState::Restarted | ChildState::Waiting => {}
+                // Процесс завершился и перезапуск не требуется
+                ChildState::Exited => target.finished = true,
             }
         }
 
         // Второе обновление для точного измерения CPU usage
-        system.refresh_all();
+        refresh_system(&mut system);
 
-        if let Some(process_data) = data_collector.get_process_data(&system, pid) {
-            if memory_usage_min == 0. && process_data.memory_usage > 0. {
-                memory_usage_min = process_data.memory_usage;
-            }
-            if process_data.memory_usage > memory_usage_min * 2. {
-                memory_color = RED;
-            } else if process_data.memory_usage <= memory_usage_min * 2. {
-                memory_color = GREEN;
+        let elapsed = start_time.elapsed().as_millis() as u64;
+        for (index, target) in targets.iter_mut().enumerate() {
+            // Завершившиеся и ожидающие перезапуска процессы не опрашиваем
+            if target.finished || target.restart_at.is_some() {
+                continue;
             }
+            if target.sample(&system) {
+                if let Some(ref process_data) = target.last {
+                    let cgroup = target.cgroup.as_deref();
+                    exporter.export(target.tick, target.pid, cgroup, process_data)?;
+                    recorder.record(elapsed, index, target.pid.as_u32(), cgroup, process_data)?;
+                }
 
-            if process_data.memory_usage > max {
-                max = process_data.memory_usage;
-            }
+                for line in target.collect_marks() {
+                    let message = format!("Mark for {}: {}", target.label(), line);
+                    logger.log(&message)?;
+                }
 
-            // Обновление максимальных значений CPU и памяти
-            if process_data.cpu_usage > max_cpu_usage {
-                max_cpu_usage = process_data.cpu_usage;
-            }
-            if process_data.memory_usage > max_memory_usage {
-                max_memory_usage = process_data.memory_usage;
+                for rule in target.check_alerts(Instant::now()) {
+                    let value = target.last.as_ref().map_or(0., |data| rule.value(data));
+                    let message = format!(
+                        "Alert for {}: {} (value: {:.2})",
+                        target.label(),
+                        rule,
+                        value
+                    );
+                    println!("{}", message);
+                    logger.log(&message)?;
+                    let group = target.child.is_some();
+                    let cgroup = target.cgroup.as_deref();
+                    if let Some(code) =
+                        alert::run_action(&rule, &system, target.pid, group, cgroup, value)
+                    {
+                        exit_code = Some(code);
+                    }
+                }
+            } else {
+                if !args.watch {
+                    target.finished = true;
+                    continue;
+                }
+                term.move_cursor_to(0, 0).unwrap();
+                let old_pid = target.pid;
+                match target.reattach(&system) {
+                    Some(new_pid) => {
+                        let message =
+                            format!("Process {} restarted with PID: {}", old_pid, new_pid);
+                        println!("{}", message);
+                        logger.log(&message)?;
+                    }
+                    None => println!("Waiting process... {:?}", target.pid),
+                }
             }
+        }
 
-            let x_label = x_label_format(
-                tick,
-                process_data.cpu_usage,
-                process_data.memory_usage,
-                process_data.total_written_bytes,
-                process_data.total_read_bytes,
-                process_data.name.clone(),
-                process_data.status.clone(),
-                args.disk_write,
-                args.disk_read,
-            );
-
-            data_collector.update_cpu_data(process_data.cpu_usage);
-            data_collector.update_memory_data(process_data.memory_usage);
-            data_collector.update_disk_read_data(process_data.total_read_bytes);
-            data_collector.update_disk_write_data(process_data.total_written_bytes);
-
-            if !args.nochart {
-                logger.log(&x_label)?;
+        // Завершаем программу, если все процессы завершились или сработало правило с exit
+        if exit_code.is_some() || targets.iter().all(|target| target.finished) {
+            break;
+        }
 
-                term.move_cursor_to(0, 0).unwrap();
+        if budgets.runtime_exceeded(start_time.elapsed()) {
+            println!("Max runtime exceeded, stopping.");
+            break;
+        }
 
-                chart_manager
-                    .set_cpu_data(&data_collector.cpu_data)
-                    .set_memory_data(&data_collector.memory_data)
-                    .set_disk_read_data(&data_collector.disk_write_data)
-                    .set_disk_write_data(&data_collector.disk_read_data)
-                    .set_x_label(x_label)
-                    .set_cpu_usage(process_data.cpu_usage)
-                    .set_memory_usage(process_data.memory_usage)
-                    .set_memory_color(memory_color)
-                    .set_disk_write(args.disk_write)
-                    .set_disk_read(args.disk_read)
-                    .set_max(max)
-                    .draw_chart();
+        if !args.nochart && (redraw || !view.paused) {
+            draw_targets(
+                &term,
+                &mut chart_manager,
+                &targets,
+                &args,
+                &view,
+                &mut logger,
+                args.host.then_some(&system),
+            )?;
+            if keyboard.enabled() {
+                term.clear_line().unwrap();
+                println!("{}", view.status_line());
             }
+        }
 
-            tick += 1;
-        } else {
-            if !args.watch {
-                break;
+        // Ждём следующего обновления, обрабатывая нажатия клавиш
+        let action = keyboard.wait(Duration::from_millis(args.sleep));
+        redraw = action.is_some();
+        match action {
+            Some(Action::Quit) => running.store(false, Ordering::SeqCst),
+            Some(Action::Restart) => {
+                for target in targets.iter_mut() {
+                    target.restart(&mut system, &args.workdir)?;
+                }
             }
-            let term = console::Term::stdout();
-            term.show_cursor().unwrap();
-            let new_pid = system
-                .processes()
-                .iter()
-                .find(|(_, p)| p.pid() == pid)
-                .map(|(&pid, _)| pid.as_u32());
-
-            term.move_cursor_to(0, 0).unwrap();
-            println!("Waiting process... {:?}", pid);
-
-            if let Some(new_pid_value) = new_pid {
-                println!("Process restarted with PID: {}", new_pid_value);
-                pid = Pid::from_u32(new_pid_value);
+            Some(Action::Kill) => {
+                for target in targets.iter_mut().filter(|target| target.child.is_some()) {
+                    target.terminate(&mut system);
+                    target.finished = true;
+                }
+            }
+            Some(Action::Switch) => {
+                keyboard.suspend();
+                term.show_cursor().unwrap();
+                term.clear_screen().unwrap();
+                let picked = pick_targets(&ProcessManager::new(), &[String::new()]);
+                if !picked.is_empty() {
+                    switched_out.append(&mut targets);
+                    targets = picked;
+                    configure_targets(&mut targets, &args);
+                }
+                keyboard.resume();
+                term.hide_cursor().unwrap();
+                term.clear_screen().unwrap();
             }
+            Some(ref action) => {
+                view.apply(action);
+                term.clear_screen().unwrap();
+            }
+            None => {}
         }
-
-        thread::sleep(Duration::from_millis(args.sleep));
     }
+    keyboard.suspend();
+
+    switched_out.append(&mut targets);
+    let mut targets = switched_out;
 
-    // Если был запущен процесс через args.command, завершаем его
-    if let Some(mut child_process) = child {
-        let _ = child_process.kill();
-        println!("Process with PID {} has been terminated.", pid);
+    // Если процессы были запущены через args.command, завершаем их
+    for target in targets.iter_mut() {
+        target.terminate(&mut system);
     }
 
     // Вывод статистики
     let elapsed_time = start_time.elapsed();
-    let min_memory_usage = data_collector
-        .memory_data
-        .iter()
-        .map(|&(_, value)| value) // Извлекаем второе значение из кортежа
-        .fold(f32::INFINITY, f32::min); // Находим минимальное значение
-                                        // Среднее использование CPU
-    let avg_cpu_usage = data_collector
-        .cpu_data
-        .iter()
-        .map(|&(_, value)| value) // Извлекаем второе значение из кортежа
-        .sum::<f32>()
-        / data_collector.cpu_data.len() as f32;
-
-    // Среднее использование памяти
-    let avg_memory_usage = data_collector
-        .memory_data
-        .iter()
-        .map(|&(_, value)| value) // Извлекаем второе значение из кортежа
-        .sum::<f32>()
-        / data_collector.memory_data.len() as f32;
+    let summaries = print_summaries(&targets, args.watch, elapsed_time);
 
-    // Общее количество записанных данных на диск
-    let total_disk_write = data_collector
-        .disk_write_data
-        .iter()
-        .map(|&(_, value)| value) // Извлекаем второе значение из кортежа
-        .sum::<f32>();
+    if let Some(code) = exit_code {
+        std::process::exit(code);
+    }
 
-    // Общее количество прочитанных данных на диск
-    let total_disk_read = data_collector
-        .disk_read_data
-        .iter()
-        .map(|&(_, value)| value) // Извлекаем второе значение из кортежа
-        .sum::<f32>();
+    // Проверяем бюджеты ресурсов
+    let breaches = budgets.check(&summaries, elapsed_time);
+    if !breaches.is_empty() {
+        println!("\nBudget exceeded:");
+        for breach in &breaches {
+            println!("  {}", breach);
+        }
+        std::process::exit(BUDGET_EXIT_CODE);
+    }
 
-    // Минимальное использование CPU
-    let min_cpu_usage = data_collector
-        .cpu_data
-        .iter()
-        .map(|&(_, value)| value) // Извлекаем второе значение из кортежа
-        .fold(f32::INFINITY, f32::min); // Находим минимальное значение
-    println!("\nProgram finished.");
-    if args.watch && !args.command.is_empty() {
-        println!("Process restarts: {}", restart_count);
+    // Пробрасываем код выхода дочернего процесса
+    if args.propagate_exit_code {
+        let failed = targets
+            .iter()
+            .filter_map(|target| target.exit_status)
+            .find(|status| !status.success());
+        if let Some(status) = failed {
+            std::process::exit(status.code().unwrap_or(1));
+        }
     }
-    // println!("CPU cores: {}", system.cpus().len());
-    // println!("Total memory: {:.2} MB", system.total_memory() as f32 / 1024.0 / 1024.0);
-    println!("Min Memory Usage: {:.2} MB", min_memory_usage);
-    println!("Min CPU Usage: {:.2}%", min_cpu_usage);
-    println!("Max CPU Usage: {:.2}%", max_cpu_usage);
-    println!("Average CPU Usage: {:.2}%", avg_cpu_usage);
-    println!("Max Memory Usage: {:.2} MB", max_memory_usage);
-    println!("Average Memory Usage: {:.2} MB", avg_memory_usage);
-    println!("Total Disk Write: {:.2} MB", total_disk_write);
-    println!("Total Disk Read: {:.2} MB", total_disk_read);
-    println!("Total runtime: {}", format_duration(elapsed_time));
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_runtime_rejects_invalid_durations() {
+        let args = Args::try_parse_from(["monit-tool", "--max-runtime=5s", "-c", "true"]).unwrap();
+        assert_eq!(args.max_runtime, Some(Duration::from_secs(5)));
+
+        for value in ["-5s", "nan", "1e30s"] {
+            let error = Args::try_parse_from([
+                "monit-tool",
+                "--nochart",
+                &format!("--max-runtime={}", value),
+                "-c",
+                "true",
+            ])
+            .unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::ValueValidation);
+        }
+    }
+
+    #[test]
+    fn budgets_reject_invalid_sizes_and_percentages() {
+        for flag in ["--max-memory", "--max-disk-write", "--max-avg-cpu"] {
+            for value in ["nan", "inf", "-1"] {
+                let error = Args::try_parse_from([
+                    "monit-tool",
+                    "--nochart",
+                    &format!("{}={}", flag, value),
+                    "-c",
+                    "true",
+                ])
+                .unwrap_err();
+                assert_eq!(
+                    error.kind(),
+                    ErrorKind::ValueValidation,
+                    "{} {}",
+                    flag,
+                    value
+                );
+            }
+        }
+    }
+}
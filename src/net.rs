@@ -0,0 +1,163 @@
+use sysinfo::Pid;
+
+// Sockets of a process tree and the traffic of its network namespaces
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NetStats {
+    // All socket descriptors (TCP, UDP, unix, ...) of the tree
+    pub sockets: u64,
+    // TCP sockets of the tree by state
+    pub established: u64,
+    pub listen: u64,
+    pub time_wait: u64,
+    pub close_wait: u64,
+    // Bytes received and sent by all interfaces except `lo`, summed over the
+    // network namespaces of the tree
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+impl NetStats {
+    #[cfg(target_os = "linux")]
+    pub fn read(pids: &[Pid]) -> Self {
+        use std::collections::{HashMap, HashSet};
+        use std::fs;
+
+        let mut stats = NetStats::default();
+
+        // Сокеты процессов видны в /proc/<pid>/fd как ссылки `socket:[<inode>]`
+        let mut inodes = HashSet::new();
+        let mut namespaces = HashMap::new();
+        for pid in pids {
+            if let Ok(entries) = fs::read_dir(format!("/proc/{}/fd", pid)) {
+                for entry in entries.flatten() {
+                    let Ok(link) = fs::read_link(entry.path()) else {
+                        continue;
+                    };
+                    let link = link.to_string_lossy();
+                    if let Some(inode) = link
+                        .strip_prefix("socket:[")
+                        .and_then(|inode| inode.strip_suffix(']'))
+                    {
+                        inodes.insert(inode.to_string());
+                    }
+                }
+            }
+            if let Ok(namespace) = fs::read_link(format!("/proc/{}/ns/net", pid)) {
+                namespaces.entry(namespace).or_insert(*pid);
+            }
+        }
+        stats.sockets = inodes.len() as u64;
+
+        for pid in namespaces.values() {
+            let tables: Vec<String> = ["tcp", "tcp6"]
+                .iter()
+                .filter_map(|file| fs::read_to_string(format!("/proc/{}/net/{}", pid, file)).ok())
+                .collect();
+            stats.count_tcp(&tables, &inodes);
+
+            // Строки вида `eth0: <8 полей приёма> <8 полей передачи>`
+            let Ok(dev) = fs::read_to_string(format!("/proc/{}/net/dev", pid)) else {
+                continue;
+            };
+            for line in dev.lines().skip(2) {
+                let Some((interface, counters)) = line.split_once(':') else {
+                    continue;
+                };
+                if interface.trim() == "lo" {
+                    continue;
+                }
+                let counters: Vec<u64> = counters
+                    .split_whitespace()
+                    .map(|value| value.parse().unwrap_or(0))
+                    .collect();
+                if counters.len() >= 9 {
+                    stats.rx_bytes += counters[0];
+                    stats.tx_bytes += counters[8];
+                }
+            }
+        }
+
+        stats
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn read(_pids: &[Pid]) -> Self {
+        NetStats::default()
+    }
+
+    // Counts the TCP sockets of the tree in the `/proc/net/tcp{,6}` tables of one
+    // namespace. A TIME_WAIT socket has no descriptor any more (its inode is 0),
+    // so it is attributed to the tree if its local port is used by a socket of
+    // the tree, e.g. by the listening socket of a server. Client-side TIME_WAIT
+    // sockets whose descriptor is already closed cannot be attributed
+    #[cfg(target_os = "linux")]
+    fn count_tcp(&mut self, tables: &[String], inodes: &std::collections::HashSet<String>) {
+        // Поля: sl, local_address, rem_address, st, ..., inode (десятое)
+        let rows: Vec<Vec<&str>> = tables
+            .iter()
+            .flat_map(|table| table.lines().skip(1))
+            .map(|line| line.split_whitespace().collect::<Vec<_>>())
+            .filter(|fields| fields.len() >= 10)
+            .collect();
+        let port = |fields: &[&str]| fields[1].rsplit_once(':').map(|(_, port)| port.to_string());
+
+        let mut ports = std::collections::HashSet::new();
+        for fields in rows.iter().filter(|fields| inodes.contains(fields[9])) {
+            ports.extend(port(fields));
+            match fields[3] {
+                "01" => self.established += 1,
+                "06" => self.time_wait += 1,
+                "08" => self.close_wait += 1,
+                "0A" => self.listen += 1,
+                _ => {}
+            }
+        }
+        self.time_wait += rows
+            .iter()
+            .filter(|fields| fields[3] == "06" && fields[9] == "0")
+            .filter(|fields| port(fields).is_some_and(|port| ports.contains(&port)))
+            .count() as u64;
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const TCP: &str = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 1001 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:1F90 0100007F:D431 01 00000000:00000000 00:00000000 00000000  1000        0 1002 1 0000000000000000 20 4 30 10 -1
+   2: 0100007F:1F90 0100007F:D432 06 00000000:00000000 03:00001770 00000000     0        0 0 3 0000000000000000
+   3: 0100007F:1F90 0100007F:D433 06 00000000:00000000 03:00001770 00000000     0        0 0 3 0000000000000000
+   4: 0100007F:9C40 0100007F:0050 06 00000000:00000000 03:00001770 00000000     0        0 0 3 0000000000000000
+   5: 00000000:0050 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 2001 1 0000000000000000 100 0 0 10 0
+   6: 0100007F:D440 0100007F:0050 08 00000000:00000000 00:00000000 00000000  1000        0 1003 1 0000000000000000 20 4 30 10 -1
+";
+
+    const TCP6: &str = "\
+  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000001000000:1F90 00000000000000000000000001000000:D450 06 00000000:00000000 03:00001770 00000000     0        0 0 3 0000000000000000
+";
+
+    #[test]
+    fn counts_tcp_sockets_of_the_tree() {
+        let inodes: HashSet<String> = ["1001", "1002", "1003"].map(String::from).into();
+        let mut stats = NetStats::default();
+        stats.count_tcp(&[TCP.to_string(), TCP6.to_string()], &inodes);
+
+        assert_eq!(stats.listen, 1);
+        assert_eq!(stats.established, 1);
+        assert_eq!(stats.close_wait, 1);
+        // TIME_WAIT на порту 8080 (1F90) наши, на чужом порту 40000 (9C40) нет
+        assert_eq!(stats.time_wait, 3);
+    }
+
+    #[test]
+    fn ignores_time_wait_without_sockets_of_the_tree() {
+        let mut stats = NetStats::default();
+        stats.count_tcp(&[TCP.to_string()], &HashSet::new());
+        assert_eq!(stats.listen + stats.established + stats.time_wait, 0);
+    }
+}
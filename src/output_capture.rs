@@ -0,0 +1,119 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::Child;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// Number of lines kept in memory for the output pane
+const KEEP_LINES: usize = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+// Line that matched one of the `--mark` patterns
+pub struct OutputMatch {
+    pub pattern: String,
+    pub line: String,
+}
+
+#[derive(Default)]
+struct Shared {
+    lines: VecDeque<(Stream, String)>,
+    matches: Vec<OutputMatch>,
+}
+
+// Reads stdout and stderr of the monitored command in background threads,
+// keeps the last lines, tees them to files and reports pattern matches
+#[derive(Clone)]
+pub struct OutputCapture {
+    shared: Arc<Mutex<Shared>>,
+    stdout_file: Option<Arc<Mutex<File>>>,
+    stderr_file: Option<Arc<Mutex<File>>>,
+    patterns: Arc<Vec<String>>,
+}
+
+impl OutputCapture {
+    pub fn new(tee_prefix: Option<&str>, patterns: &[String]) -> io::Result<Self> {
+        let create = |suffix: &str| -> io::Result<Option<Arc<Mutex<File>>>> {
+            match tee_prefix {
+                Some(prefix) => {
+                    let file = File::create(format!("{}.{}.log", prefix, suffix))?;
+                    Ok(Some(Arc::new(Mutex::new(file))))
+                }
+                None => Ok(None),
+            }
+        };
+
+        Ok(OutputCapture {
+            shared: Arc::new(Mutex::new(Shared::default())),
+            stdout_file: create("stdout")?,
+            stderr_file: create("stderr")?,
+            patterns: Arc::new(patterns.to_vec()),
+        })
+    }
+
+    // Starts reading the piped streams of a (re)started child
+    pub fn attach(&self, child: &mut Child) {
+        if let Some(stdout) = child.stdout.take() {
+            self.spawn_reader(stdout, Stream::Stdout, self.stdout_file.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            self.spawn_reader(stderr, Stream::Stderr, self.stderr_file.clone());
+        }
+    }
+
+    fn spawn_reader<R: Read + Send + 'static>(
+        &self,
+        reader: R,
+        stream: Stream,
+        file: Option<Arc<Mutex<File>>>,
+    ) {
+        let shared = self.shared.clone();
+        let patterns = self.patterns.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(reader).split(b'\n') {
+                let Ok(line) = line else {
+                    break;
+                };
+                let line = String::from_utf8_lossy(&line)
+                    .trim_end_matches('\r')
+                    .to_string();
+
+                if let Some(ref file) = file {
+                    let _ = writeln!(file.lock().unwrap(), "{}", line);
+                }
+
+                let mut shared = shared.lock().unwrap();
+                for pattern in patterns
+                    .iter()
+                    .filter(|pattern| line.contains(pattern.as_str()))
+                {
+                    shared.matches.push(OutputMatch {
+                        pattern: pattern.clone(),
+                        line: line.clone(),
+                    });
+                }
+                shared.lines.push_back((stream, line));
+                if shared.lines.len() > KEEP_LINES {
+                    shared.lines.pop_front();
+                }
+            }
+        });
+    }
+
+    // Last `count` lines of both streams, oldest first
+    pub fn last_lines(&self, count: usize) -> Vec<(Stream, String)> {
+        let shared = self.shared.lock().unwrap();
+        let skip = shared.lines.len().saturating_sub(count);
+        shared.lines.iter().skip(skip).cloned().collect()
+    }
+
+    // Pattern matches since the previous call
+    pub fn take_matches(&self) -> Vec<OutputMatch> {
+        std::mem::take(&mut self.shared.lock().unwrap().matches)
+    }
+}
@@ -0,0 +1,167 @@
+use crate::output_capture::Stream;
+use monit_tool::data_collector::{ProcessData, ProcessNode};
+use std::collections::VecDeque;
+use sysinfo::{Pid, System};
+
+// Width of one core in the per-core line, e.g. `▅ 63% `
+const CORE_CELL: usize = 7;
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+// Shades of the heatmap cells from idle to a fully used core
+const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
+// Prints exactly `rows` lines padded to the terminal width, so the previous
+// frame is fully overwritten
+fn print_padded(mut lines: Vec<String>, rows: usize) {
+    let (_, width) = console::Term::stdout().size();
+    let width = (width as usize).saturating_sub(1);
+
+    lines.resize(rows, String::new());
+    for line in lines {
+        let line = console::truncate_str(&line, width, "…");
+        println!("{:<width$}", line, width = width);
+    }
+}
+
+// Prints the process tree below the chart
+pub fn draw_tree(nodes: &[ProcessNode], rows: usize) {
+    let mut lines = vec![format!(
+        "{:>8} {:>8} {:>10} {:<10} COMMAND",
+        "PID", "CPU %", "RSS MB", "STATUS"
+    )];
+    for node in nodes.iter().take(rows) {
+        lines.push(format!(
+            "{:>8} {:>8.2} {:>10.2} {:<10} {}{}",
+            node.pid.as_u32(),
+            node.cpu_usage,
+            node.memory_usage,
+            node.status,
+            "  ".repeat(node.depth),
+            node.name
+        ));
+    }
+    if nodes.len() > rows {
+        lines.push(format!("... {} more", nodes.len() - rows));
+    }
+    print_padded(lines, rows + 2);
+}
+
+// Prints the last lines of the command output below the chart
+pub fn draw_output(lines: &[(Stream, String)], rows: usize) {
+    let lines = lines
+        .iter()
+        .map(|(stream, line)| match stream {
+            Stream::Stdout => format!("  {}", line),
+            Stream::Stderr => format!("! {}", line),
+        })
+        .collect();
+    print_padded(lines, rows);
+}
+
+// Prints the host load above the charts and the share the monitored trees take of it
+pub fn draw_host(system: &System, targets: &[(Pid, &ProcessData)]) {
+    let gb = |bytes: u64| bytes as f32 / 1024.0 / 1024.0 / 1024.0;
+    let cores = system.cpus().len().max(1);
+    let load = System::load_average();
+    let mut lines = vec![
+        format!(
+            "Host: {} cores, CPU {:.1}%, load average {:.2} {:.2} {:.2}",
+            cores,
+            system.global_cpu_usage(),
+            load.one,
+            load.five,
+            load.fifteen
+        ),
+        format!(
+            "Memory {:.2}/{:.2} GB used, {:.2} GB available | Swap {:.2}/{:.2} GB used",
+            gb(system.used_memory()),
+            gb(system.total_memory()),
+            gb(system.available_memory()),
+            gb(system.used_swap()),
+            gb(system.total_swap())
+        ),
+    ];
+
+    let per_line = cores_per_line();
+    let cells: Vec<String> = system
+        .cpus()
+        .iter()
+        .map(|cpu| {
+            let usage = cpu.cpu_usage().clamp(0., 100.);
+            let bar = BARS
+                [((usage / 100. * (BARS.len() - 1) as f32).round() as usize).min(BARS.len() - 1)];
+            format!("{} {:>3.0}% ", bar, usage)
+        })
+        .collect();
+    for chunk in cells.chunks(per_line) {
+        lines.push(chunk.concat());
+    }
+
+    // Доля дерева: CPU от мощности всех ядер и от занятой, память и swap от занятых
+    let share = |part: f32, whole: f32| if whole > 0. { part / whole * 100. } else { 0. };
+    let used_cpu = system.global_cpu_usage() * cores as f32;
+    let used_memory = system.used_memory() as f32 / 1024.0 / 1024.0;
+    let used_swap = system.used_swap() as f32 / 1024.0 / 1024.0;
+    let total_memory = system.total_memory() as f32 / 1024.0 / 1024.0;
+    for (pid, process_data) in targets {
+        lines.push(format!(
+            "[{}] share: CPU {:.1}% of host ({:.1}% of used), Memory {:.1}% of total ({:.1}% of used), Swap {:.1}% of used",
+            pid,
+            share(process_data.cpu_usage, cores as f32 * 100.),
+            share(process_data.cpu_usage, used_cpu),
+            share(process_data.memory_usage, total_memory),
+            share(process_data.memory_usage, used_memory),
+            share(process_data.swap, used_swap)
+        ));
+    }
+
+    let rows = lines.len();
+    print_padded(lines, rows);
+}
+
+// Number of lines `draw_host` prints
+pub fn host_rows(system: &System, targets: usize) -> usize {
+    2 + system.cpus().len().div_ceil(cores_per_line()) + targets
+}
+
+fn cores_per_line() -> usize {
+    let (_, width) = console::Term::stdout().size();
+    (width as usize / CORE_CELL).max(1)
+}
+
+// Prints one row per core with the usage the tree caused on it over time,
+// the newest sample on the right
+pub fn draw_heatmap(core_data: &VecDeque<Vec<f32>>, window: usize) {
+    let (_, width) = console::Term::stdout().size();
+    let columns = window.min((width as usize).saturating_sub(12)).max(1);
+    let cores = core_data.back().map_or(0, |per_core| per_core.len());
+    let skip = core_data.len().saturating_sub(columns);
+
+    let mut lines = vec![format!(
+        "Per-core usage of the threads ({} low ... {} 100%)",
+        SHADES[1], SHADES[4]
+    )];
+    for core in 0..cores {
+        let cells: String = core_data
+            .iter()
+            .skip(skip)
+            .map(|per_core| {
+                let usage = per_core.get(core).copied().unwrap_or(0.).clamp(0., 100.);
+                if usage <= 0. {
+                    SHADES[0]
+                } else {
+                    SHADES[1 + ((usage / 100. * 3.).round() as usize).min(3)]
+                }
+            })
+            .collect();
+        let usage = core_data.back().map_or(0., |per_core| per_core[core]);
+        lines.push(format!(
+            "{:>4} {:>4.0}% {:>width$}",
+            core,
+            usage,
+            cells,
+            width = columns
+        ));
+    }
+    let rows = lines.len();
+    print_padded(lines, rows);
+}
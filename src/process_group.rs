@@ -0,0 +1,55 @@
+use std::process::Command;
+use sysinfo::{Pid, Signal};
+
+// Starts the command in its own process group, so the whole tree it spawns
+// can be signalled at once and does not get the terminal's Ctrl+C directly
+#[cfg(unix)]
+pub fn isolate(command: &mut Command) -> &mut Command {
+    use std::os::unix::process::CommandExt;
+    command.process_group(0)
+}
+
+#[cfg(not(unix))]
+pub fn isolate(command: &mut Command) -> &mut Command {
+    command
+}
+
+// Sends the signal to every process of the group led by `leader`,
+// returns false if the group does not exist (any more)
+#[cfg(unix)]
+pub fn signal(leader: Pid, signal: Signal) -> bool {
+    // SAFETY: kill only takes plain integers
+    unsafe { libc::kill(-(leader.as_u32() as libc::pid_t), signal_number(signal)) == 0 }
+}
+
+#[cfg(not(unix))]
+pub fn signal(_leader: Pid, _signal: Signal) -> bool {
+    false
+}
+
+// Whether at least one process (zombies included) is still in the group
+#[cfg(unix)]
+pub fn alive(leader: Pid) -> bool {
+    // SAFETY: signal 0 only checks that the group exists
+    unsafe { libc::kill(-(leader.as_u32() as libc::pid_t), 0) == 0 }
+}
+
+#[cfg(not(unix))]
+pub fn alive(_leader: Pid) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn signal_number(signal: Signal) -> libc::c_int {
+    match signal {
+        Signal::Kill => libc::SIGKILL,
+        Signal::Interrupt => libc::SIGINT,
+        Signal::Hangup => libc::SIGHUP,
+        Signal::Quit => libc::SIGQUIT,
+        Signal::User1 => libc::SIGUSR1,
+        Signal::User2 => libc::SIGUSR2,
+        Signal::Stop => libc::SIGSTOP,
+        Signal::Continue => libc::SIGCONT,
+        _ => libc::SIGTERM,
+    }
+}
@@ -1,10 +1,22 @@
-use crate::ProcessItem;
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::FuzzySelect;
 use sysinfo::System;
 
+// A running process as listed for selection: its PID and `name - command line`
+pub struct ProcessItem {
+    pub pid: u32,
+    pub name: String,
+}
+
+// Lists the running processes and lets the user pick the ones to monitor
 pub struct ProcessManager {
-    pub(crate) system: System,
+    pub system: System,
+}
+
+impl Default for ProcessManager {
+    fn default() -> Self {
+        ProcessManager::new()
+    }
 }
 
 impl ProcessManager {
@@ -34,18 +46,46 @@ impl ProcessManager {
             .collect()
     }
 
-    pub fn select_process(&self, processes: &[ProcessItem]) -> Option<usize> {
-        let selection_items: Vec<String> = processes
-            .iter()
-            .map(|proc| format!("PID {}: {}", proc.pid, proc.name))
-            .collect();
-
-        FuzzySelect::with_theme(&ColorfulTheme::default())
-            .with_prompt("Select process")
-            .default(0)
-            .max_length(6)
-            .items(&selection_items)
-            .interact_opt()
-            .unwrap()
+    // Lets the user pick several processes one after another, Esc finishes the selection
+    pub fn select_processes(&self, processes: &[ProcessItem]) -> Vec<usize> {
+        let mut selected: Vec<usize> = Vec::new();
+
+        loop {
+            let remaining: Vec<usize> = (0..processes.len())
+                .filter(|index| !selected.contains(index))
+                .collect();
+            if remaining.is_empty() {
+                break;
+            }
+
+            let selection_items: Vec<String> = remaining
+                .iter()
+                .map(|&index| format!("PID {}: {}", processes[index].pid, processes[index].name))
+                .collect();
+
+            let prompt = if selected.is_empty() {
+                String::from("Select process")
+            } else {
+                format!(
+                    "Select another process ({} selected, Esc to start)",
+                    selected.len()
+                )
+            };
+
+            let choice = FuzzySelect::with_theme(&ColorfulTheme::default())
+                .with_prompt(prompt)
+                .default(0)
+                .max_length(6)
+                .items(&selection_items)
+                .interact_opt()
+                .unwrap();
+
+            match choice {
+                Some(position) => selected.push(remaining[position]),
+                None => break,
+            }
+        }
+
+        selected
     }
 }
@@ -0,0 +1,115 @@
+use chrono::Local;
+use monit_tool::data_collector::ProcessData;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use sysinfo::System;
+
+// Recording file format: JSON Lines, the first line is the session metadata,
+// every following line is one sample of one target.
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionMeta {
+    pub command: Vec<String>,
+    pub workdir: String,
+    pub interval: u64,
+    pub host: String,
+    pub started: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedSample {
+    // Milliseconds since the start of the session
+    pub elapsed: u64,
+    // Index of the target in the session, stays the same across restarts
+    pub target: usize,
+    pub pid: u32,
+    // Path of a cgroup target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cgroup: Option<PathBuf>,
+    pub data: ProcessData,
+}
+
+pub struct Recorder {
+    file: Option<LineWriter<File>>,
+}
+
+impl Recorder {
+    pub fn new(
+        path: Option<&str>,
+        command: &[String],
+        workdir: &str,
+        interval: u64,
+    ) -> io::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Recorder { file: None });
+        };
+
+        let meta = SessionMeta {
+            command: command.to_vec(),
+            workdir: workdir.to_string(),
+            interval,
+            host: System::host_name().unwrap_or_default(),
+            started: Local::now().to_rfc3339(),
+        };
+
+        let mut file = LineWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut file, &meta)?;
+        writeln!(file)?;
+        Ok(Recorder { file: Some(file) })
+    }
+
+    pub fn record(
+        &mut self,
+        elapsed: u64,
+        target: usize,
+        pid: u32,
+        cgroup: Option<&Path>,
+        data: &ProcessData,
+    ) -> io::Result<()> {
+        let Some(ref mut file) = self.file else {
+            return Ok(());
+        };
+
+        let sample = RecordedSample {
+            elapsed,
+            target,
+            pid,
+            cgroup: cgroup.map(Path::to_path_buf),
+            data: data.clone(),
+        };
+        serde_json::to_writer(&mut *file, &sample)?;
+        writeln!(file)
+    }
+}
+
+pub struct Recording {
+    pub meta: SessionMeta,
+    pub samples: Vec<RecordedSample>,
+}
+
+impl Recording {
+    pub fn load(path: &str) -> io::Result<Self> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+
+        let Some(header) = lines.next() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "recording is empty",
+            ));
+        };
+        let meta: SessionMeta = serde_json::from_str(&header?)?;
+
+        let mut samples = Vec::new();
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            samples.push(serde_json::from_str(&line)?);
+        }
+
+        Ok(Recording { meta, samples })
+    }
+}
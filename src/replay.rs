@@ -0,0 +1,91 @@
+use crate::chart_manager::ChartManager;
+use crate::keyboard::View;
+use crate::logger::Logger;
+use crate::recorder::Recording;
+use crate::target::Target;
+use crate::{draw_targets, print_summaries, Args};
+use chrono::{DateTime, Local};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{thread, time::Duration};
+use sysinfo::Pid;
+
+// Pushes the recorded samples back through the targets and the chart
+pub fn replay(args: &Args, path: &str, speed: f32, running: &AtomicBool) -> Result<(), io::Error> {
+    let recording = Recording::load(path)?;
+    let meta = &recording.meta;
+    let speed = if speed > 0. { speed } else { 1. };
+    // Замеры ставятся на исходное время записи
+    let started = DateTime::parse_from_rfc3339(&meta.started)
+        .map(|started| started.with_timezone(&Local))
+        .unwrap_or_else(|_| Local::now());
+
+    let term = console::Term::stdout();
+    term.hide_cursor().unwrap();
+    term.clear_screen().unwrap();
+
+    let mut logger = Logger::new(args.logging)?;
+    let mut chart_manager = ChartManager::new();
+    let view = View::new(args);
+    let mut targets: Vec<Target> = Vec::new();
+    let mut previous_elapsed = 0;
+
+    let mut samples = recording.samples.iter().peekable();
+    while let Some(sample) = samples.next() {
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+
+        if sample.elapsed > previous_elapsed {
+            let wait = (sample.elapsed - previous_elapsed) as f32 / speed;
+            thread::sleep(Duration::from_millis(wait as u64));
+            previous_elapsed = sample.elapsed;
+        }
+
+        while targets.len() <= sample.target {
+            let command = meta.command.get(targets.len()).cloned();
+            let mut target = Target::new(Pid::from_u32(sample.pid), command, None);
+            target.cgroup = sample.cgroup.clone();
+            target.track_children = args.top_children > 0;
+            target.data_collector.set_history(args.history);
+            targets.push(target);
+        }
+        let target = &mut targets[sample.target];
+        if target.pid.as_u32() != sample.pid && target.tick > 0 {
+            target.restart_count += 1;
+        }
+        target.pid = Pid::from_u32(sample.pid);
+        target.push(
+            sample.data.clone(),
+            started + chrono::Duration::milliseconds(sample.elapsed as i64),
+        );
+
+        // Перерисовываем только после того, как все цели получили свой тик
+        let tick_done = samples
+            .peek()
+            .is_none_or(|next| next.elapsed != sample.elapsed);
+        if tick_done && !args.nochart {
+            draw_targets(
+                &term,
+                &mut chart_manager,
+                &targets,
+                args,
+                &view,
+                &mut logger,
+                None,
+            )?;
+        }
+    }
+
+    println!(
+        "\nReplay of {} (host: {}, started: {}, workdir: {}, interval: {} ms)",
+        path, meta.host, meta.started, meta.workdir, meta.interval
+    );
+    print_summaries(
+        &targets,
+        targets.iter().any(|target| target.restart_count > 0),
+        Duration::from_millis(previous_elapsed),
+    );
+
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use clap::ValueEnum;
+use monit_tool::units::parse_duration;
+use std::process::ExitStatus;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum RestartMode {
+    // Restart after every exit
+    Always,
+    // Restart only if the command failed or was killed by a signal
+    OnFailure,
+}
+
+// Crash-loop limit in the form `5/60s`: give up after 5 restarts within 60 seconds
+#[derive(Clone, Copy, Debug)]
+pub struct CrashLoop {
+    pub restarts: usize,
+    pub window: Duration,
+}
+
+impl FromStr for CrashLoop {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let (restarts, window) = source
+            .split_once('/')
+            .ok_or_else(|| format!("expected <restarts>/<window>, got '{}'", source))?;
+        let restarts = restarts
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid restart count '{}'", restarts))?;
+        Ok(CrashLoop {
+            restarts,
+            window: parse_duration(window)?,
+        })
+    }
+}
+
+// How `--watch` restarts a command that exited
+#[derive(Clone, Debug)]
+pub struct RestartPolicy {
+    pub mode: RestartMode,
+    pub max_restarts: Option<u32>,
+    // Delay before the first restart, doubled after every further one up to `max_backoff`
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    pub crash_loop: Option<CrashLoop>,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy {
+            mode: RestartMode::Always,
+            max_restarts: None,
+            backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            crash_loop: None,
+        }
+    }
+}
+
+impl RestartPolicy {
+    // Whether the exit is one the command should be restarted after
+    pub fn wants_restart(&self, status: ExitStatus) -> bool {
+        self.mode == RestartMode::Always || !status.success()
+    }
+
+    // Checks the restart limits, returns why the command may not be restarted otherwise.
+    // `recent` holds the times of the previous restarts and gets the new one on success
+    pub fn check(
+        &self,
+        restarts: u32,
+        recent: &mut Vec<Instant>,
+        now: Instant,
+    ) -> Result<(), String> {
+        if let Some(max_restarts) = self.max_restarts {
+            if restarts >= max_restarts {
+                return Err(format!("reached the limit of {} restarts", max_restarts));
+            }
+        }
+        if let Some(crash_loop) = self.crash_loop {
+            recent.retain(|&time| now.duration_since(time) < crash_loop.window);
+            if recent.len() >= crash_loop.restarts {
+                return Err(format!(
+                    "crash loop, {} restarts within {:?}",
+                    recent.len(),
+                    crash_loop.window
+                ));
+            }
+        }
+        recent.push(now);
+        Ok(())
+    }
+
+    // Exponential backoff for the given attempt, randomly shortened by up to a half
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = self.backoff.as_secs_f32() * 2f32.powi(attempt.min(30) as i32);
+        let delay = delay.min(
+            self.max_backoff
+                .as_secs_f32()
+                .max(self.backoff.as_secs_f32()),
+        );
+        Duration::from_secs_f32(delay * (1. - rand::random::<f32>() / 2.))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RestartPolicy {
+        RestartPolicy {
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(8),
+            ..RestartPolicy::default()
+        }
+    }
+
+    #[test]
+    fn parses_crash_loop() {
+        let crash_loop: CrashLoop = "5/60s".parse().unwrap();
+        assert_eq!(crash_loop.restarts, 5);
+        assert_eq!(crash_loop.window, Duration::from_secs(60));
+        let crash_loop: CrashLoop = " 3 /2m".parse().unwrap();
+        assert_eq!(crash_loop.restarts, 3);
+        assert_eq!(crash_loop.window, Duration::from_secs(120));
+
+        for invalid in ["5", "x/60s", "-1/60s", "5/", "5/-1s", "5/1e30s"] {
+            assert!(invalid.parse::<CrashLoop>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn limits_the_number_of_restarts() {
+        let policy = RestartPolicy {
+            max_restarts: Some(2),
+            ..policy()
+        };
+        let mut recent = Vec::new();
+        let now = Instant::now();
+        assert!(policy.check(0, &mut recent, now).is_ok());
+        assert!(policy.check(1, &mut recent, now).is_ok());
+        assert!(policy.check(2, &mut recent, now).is_err());
+        assert_eq!(recent.len(), 2);
+
+        // Без ограничений перезапуск разрешён всегда
+        assert!(RestartPolicy::default()
+            .check(1000, &mut Vec::new(), now)
+            .is_ok());
+    }
+
+    #[test]
+    fn detects_crash_loops() {
+        let policy = RestartPolicy {
+            crash_loop: Some(CrashLoop {
+                restarts: 3,
+                window: Duration::from_secs(10),
+            }),
+            ..policy()
+        };
+        let start = Instant::now();
+        let mut recent = Vec::new();
+        for second in 0..3 {
+            let now = start + Duration::from_secs(second);
+            assert!(policy.check(second as u32, &mut recent, now).is_ok());
+        }
+        assert!(policy
+            .check(3, &mut recent, start + Duration::from_secs(3))
+            .is_err());
+        // Через окно старые перезапуски забываются
+        assert!(policy
+            .check(3, &mut recent, start + Duration::from_secs(11))
+            .is_ok());
+        assert_eq!(recent.len(), 2);
+    }
+
+    #[test]
+    fn delay_doubles_up_to_max_backoff() {
+        let policy = policy();
+        for (attempt, full) in [(0, 1.), (1, 2.), (2, 4.), (3, 8.), (4, 8.), (100, 8.)] {
+            for _ in 0..20 {
+                // Случайно укорочена не больше чем вдвое
+                let delay = policy.delay(attempt).as_secs_f32();
+                assert!(
+                    delay > full / 2. - 1e-3 && delay <= full,
+                    "attempt {}: {}",
+                    attempt,
+                    delay
+                );
+            }
+        }
+        assert!(RestartPolicy::default().delay(5).is_zero());
+
+        // Максимум меньше начальной задержки её не обрезает
+        let policy = RestartPolicy {
+            max_backoff: Duration::ZERO,
+            ..policy
+        };
+        assert!(policy.delay(3).as_secs_f32() > 0.5 - 1e-3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restarts_on_failure_only_when_asked() {
+        use std::os::unix::process::ExitStatusExt;
+        let success = ExitStatus::from_raw(0);
+        let failure = ExitStatus::from_raw(1 << 8);
+        assert!(policy().wants_restart(success));
+        assert!(policy().wants_restart(failure));
+        let policy = RestartPolicy {
+            mode: RestartMode::OnFailure,
+            ..policy()
+        };
+        assert!(!policy.wants_restart(success));
+        assert!(policy.wants_restart(failure));
+    }
+}
@@ -0,0 +1,90 @@
+use crate::data_collector::{DataCollector, ProcessData};
+use crate::refresh_system;
+use chrono::Local;
+use std::thread;
+use std::time::Duration;
+use sysinfo::{Pid, ProcessStatus, System};
+
+// Default time between two samples
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(500);
+
+// Samples the tree of one process every `interval`, without any of the
+// terminal output. Every sample is also pushed into the histories of
+// `data_collector`, so callers get the same series the charts are drawn from.
+//
+// As an iterator it waits `interval` between samples and ends once the
+// process has exited; `run` does the same with a callback that can stop it early.
+pub struct Sampler {
+    pub pid: Pid,
+    pub interval: Duration,
+    pub data_collector: DataCollector,
+    system: System,
+    sampled: bool,
+}
+
+impl Sampler {
+    pub fn new(pid: Pid) -> Self {
+        Sampler {
+            pid,
+            interval: DEFAULT_INTERVAL,
+            data_collector: DataCollector::new(),
+            system: System::new_all(),
+            sampled: false,
+        }
+    }
+
+    pub fn set_interval(&mut self, interval: Duration) -> &mut Sampler {
+        self.interval = interval;
+        self
+    }
+
+    pub fn set_history(&mut self, history: usize) -> &mut Sampler {
+        self.data_collector.set_history(history);
+        self
+    }
+
+    // Whether the process exists and has not exited yet
+    pub fn is_running(&self) -> bool {
+        self.system
+            .process(self.pid)
+            .is_some_and(|process| process.status() != ProcessStatus::Zombie)
+    }
+
+    // Takes one sample right away, None once the process is gone
+    pub fn sample(&mut self) -> Option<ProcessData> {
+        refresh_system(&mut self.system);
+        if !self.is_running() {
+            return None;
+        }
+        let process_data = self
+            .data_collector
+            .get_process_data(&self.system, self.pid)?;
+        self.data_collector
+            .set_sample_time(Local::now())
+            .update_series(&process_data);
+        Some(process_data)
+    }
+
+    // Samples until the process exits or `callback` returns false
+    pub fn run(&mut self, mut callback: impl FnMut(&ProcessData) -> bool) {
+        for process_data in self.by_ref() {
+            if !callback(&process_data) {
+                break;
+            }
+        }
+    }
+}
+
+impl Iterator for Sampler {
+    type Item = ProcessData;
+
+    fn next(&mut self) -> Option<ProcessData> {
+        // Как и основной цикл, ждём полный интервал после замера: на занятой
+        // машине обновление списка процессов само может длиться дольше интервала
+        if self.sampled {
+            thread::sleep(self.interval);
+        }
+        self.sampled = true;
+        self.sample()
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use sysinfo::{Pid, Process, ProcessStatus, System};
+
+// Describes a process that was not started by us, so it can be found again
+// after it restarts with a new PID (watch mode)
+#[derive(Clone, Debug, Default)]
+pub struct Selector {
+    // `--name` filter the process was picked with
+    pub filter: Option<String>,
+    // Command line and executable of the original process
+    pub cmdline: String,
+    pub exe: Option<PathBuf>,
+    // File with the PID, re-read every time the process is looked up
+    pub pidfile: Option<PathBuf>,
+}
+
+impl Selector {
+    pub fn from_process(process: &Process, filter: &str) -> Self {
+        Selector {
+            filter: (!filter.is_empty()).then(|| filter.to_string()),
+            cmdline: cmdline(process),
+            exe: process.exe().map(PathBuf::from),
+            pidfile: None,
+        }
+    }
+
+    // Reads the pidfile if there is one, otherwise looks for a process matching
+    // the name filter, preferring the same command line and then the same
+    // executable as the original one
+    pub fn resolve(&self, system: &System) -> Option<Pid> {
+        if let Some(ref pidfile) = self.pidfile {
+            return read_pidfile(system, pidfile);
+        }
+
+        let filter = self.filter.as_ref().map(|filter| filter.to_lowercase());
+        let matches_filter = |process: &Process| {
+            filter.as_ref().is_none_or(|filter| {
+                format!(
+                    "{} - {}",
+                    process.name().to_string_lossy(),
+                    cmdline(process)
+                )
+                .to_lowercase()
+                .contains(filter)
+            })
+        };
+
+        if !self.cmdline.is_empty() {
+            if let Some(pid) = find(system, |process| {
+                matches_filter(process) && cmdline(process) == self.cmdline
+            }) {
+                return Some(pid);
+            }
+        }
+        if let Some(ref exe) = self.exe {
+            if let Some(pid) = find(system, |process| {
+                matches_filter(process) && process.exe() == Some(exe.as_path())
+            }) {
+                return Some(pid);
+            }
+        }
+        // Без фильтра по имени искать больше не по чему
+        filter.as_ref()?;
+        find(system, matches_filter)
+    }
+}
+
+fn read_pidfile(system: &System, path: &Path) -> Option<Pid> {
+    let pid = Pid::from_u32(fs::read_to_string(path).ok()?.trim().parse().ok()?);
+    system
+        .process(pid)
+        .filter(|process| process.status() != ProcessStatus::Zombie)
+        .map(|process| process.pid())
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref pidfile) = self.pidfile {
+            write!(f, "pidfile {}", pidfile.display())
+        } else if !self.cmdline.is_empty() {
+            write!(f, "command line '{}'", self.cmdline)
+        } else if let Some(ref exe) = self.exe {
+            write!(f, "executable {}", exe.display())
+        } else {
+            write!(f, "name '{}'", self.filter.as_deref().unwrap_or_default())
+        }
+    }
+}
+
+pub fn cmdline(process: &Process) -> String {
+    process
+        .cmd()
+        .iter()
+        .map(|s| s.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Newest matching process whose parent does not match as well, so workers
+// forked by the restarted process are not picked instead of it
+fn find(system: &System, matches: impl Fn(&Process) -> bool) -> Option<Pid> {
+    let own_pid = sysinfo::get_current_pid().ok();
+    let candidates: Vec<&Process> = system
+        .processes()
+        .values()
+        .filter(|process| Some(process.pid()) != own_pid && process.thread_kind().is_none())
+        .filter(|process| process.status() != ProcessStatus::Zombie)
+        .filter(|process| matches(process))
+        .collect();
+
+    candidates
+        .iter()
+        .filter(|process| {
+            !candidates
+                .iter()
+                .any(|candidate| Some(candidate.pid()) == process.parent())
+        })
+        .max_by_key(|process| process.start_time())
+        .map(|process| process.pid())
+}
@@ -0,0 +1,303 @@
+use crate::alert::Metric;
+use crate::colors::{
+    BLUE, BROWN, CORAL, CYAN, GOLD, GREEN, LAVENDER, LIME, MAGENTA, MINT, OLIVE, ORANGE, PINK,
+    PURPLE, SILVER, SKY, TEAL, WHITE, YELLOW,
+};
+use crate::data_collector::ProcessData;
+use rgb::RGB8;
+use std::fmt;
+
+// Everything needed to collect, chart and describe one metric. New series
+// only have to be added to `REGISTRY`
+pub struct SeriesSpec {
+    // Name used by `--metrics` and `--chart`
+    pub name: &'static str,
+    pub label: &'static str,
+    pub unit: &'static str,
+    pub decimals: usize,
+    pub color: (&'static str, RGB8),
+    // Alert metric whose rules turn the line red
+    pub alert: Option<Metric>,
+    pub value: fn(&ProcessData) -> f32,
+    // Additional values shown in the legend, e.g. totals
+    pub extra: Option<fn(&ProcessData) -> String>,
+}
+
+impl SeriesSpec {
+    pub fn format(&self, value: f32) -> String {
+        format!("{:.*} {}", self.decimals, value, self.unit)
+            .trim_end()
+            .to_string()
+    }
+
+    // Legend entry of the line, e.g. `ORANGE = CPU (12.50 %)`
+    pub fn legend(&self, process_data: &ProcessData) -> String {
+        let mut legend = self.format((self.value)(process_data));
+        if let Some(extra) = self.extra {
+            legend += &format!(", {}", extra(process_data));
+        }
+        format!("{} = {} ({})", self.color.0, self.label, legend)
+    }
+}
+
+impl fmt::Debug for SeriesSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+// All series in the order they are drawn; every target keeps a history of each.
+// Colors are unique, so any combination of series can share the chart
+pub static REGISTRY: [SeriesSpec; 19] = [
+    SeriesSpec {
+        name: "cpu",
+        label: "CPU",
+        unit: "%",
+        decimals: 2,
+        color: ("ORANGE", ORANGE),
+        alert: Some(Metric::Cpu),
+        value: |data| data.cpu_usage,
+        extra: None,
+    },
+    SeriesSpec {
+        name: "rss",
+        label: "Memory",
+        unit: "MB",
+        decimals: 2,
+        color: ("GREEN/RED", GREEN),
+        alert: Some(Metric::Memory),
+        value: |data| data.memory_usage,
+        extra: None,
+    },
+    SeriesSpec {
+        name: "disk-write",
+        label: "Disk write",
+        unit: "MB/s",
+        decimals: 2,
+        color: ("PURPLE", PURPLE),
+        alert: Some(Metric::DiskWrite),
+        value: |data| data.disk_write_rate,
+        extra: Some(|data| format!("Total: {:.2} MB", data.total_written_bytes)),
+    },
+    SeriesSpec {
+        name: "disk-read",
+        label: "Disk read",
+        unit: "MB/s",
+        decimals: 2,
+        color: ("BLUE", BLUE),
+        alert: Some(Metric::DiskRead),
+        value: |data| data.disk_read_rate,
+        extra: Some(|data| format!("Total: {:.2} MB", data.total_read_bytes)),
+    },
+    SeriesSpec {
+        name: "net-rx",
+        label: "Net rx",
+        unit: "MB/s",
+        decimals: 2,
+        color: ("CYAN", CYAN),
+        alert: None,
+        value: |data| data.net_rx_rate,
+        extra: Some(|data| format!("Total: {:.2} MB", data.total_net_rx)),
+    },
+    SeriesSpec {
+        name: "net-tx",
+        label: "Net tx",
+        unit: "MB/s",
+        decimals: 2,
+        color: ("MAGENTA", MAGENTA),
+        alert: None,
+        value: |data| data.net_tx_rate,
+        extra: Some(|data| format!("Total: {:.2} MB", data.total_net_tx)),
+    },
+    SeriesSpec {
+        name: "sockets",
+        label: "Sockets",
+        unit: "",
+        decimals: 0,
+        color: ("WHITE", WHITE),
+        alert: None,
+        value: |data| data.sockets as f32,
+        extra: Some(|data| {
+            format!(
+                "ESTABLISHED {}, LISTEN {}, TIME_WAIT {}, CLOSE_WAIT {}",
+                data.tcp_established, data.tcp_listen, data.tcp_time_wait, data.tcp_close_wait
+            )
+        }),
+    },
+    SeriesSpec {
+        name: "vmem",
+        label: "Virtual memory",
+        unit: "MB",
+        decimals: 2,
+        color: ("SKY", SKY),
+        alert: None,
+        value: |data| data.virtual_memory,
+        extra: None,
+    },
+    SeriesSpec {
+        name: "swap",
+        label: "Swap",
+        unit: "MB",
+        decimals: 2,
+        color: ("YELLOW", YELLOW),
+        alert: None,
+        value: |data| data.swap,
+        extra: None,
+    },
+    SeriesSpec {
+        name: "pss",
+        label: "PSS",
+        unit: "MB",
+        decimals: 2,
+        color: ("LAVENDER", LAVENDER),
+        alert: None,
+        value: |data| data.pss,
+        extra: None,
+    },
+    SeriesSpec {
+        name: "uss",
+        label: "USS",
+        unit: "MB",
+        decimals: 2,
+        color: ("SILVER", SILVER),
+        alert: None,
+        value: |data| data.uss,
+        extra: None,
+    },
+    SeriesSpec {
+        name: "threads",
+        label: "Threads",
+        unit: "",
+        decimals: 0,
+        color: ("TEAL", TEAL),
+        alert: None,
+        value: |data| data.threads as f32,
+        extra: None,
+    },
+    SeriesSpec {
+        name: "fds",
+        label: "FDs",
+        unit: "",
+        decimals: 0,
+        color: ("PINK", PINK),
+        alert: None,
+        value: |data| data.fds as f32,
+        extra: None,
+    },
+    SeriesSpec {
+        name: "ctx-voluntary",
+        label: "Voluntary switches",
+        unit: "/s",
+        decimals: 0,
+        color: ("LIME", LIME),
+        alert: None,
+        value: |data| data.voluntary_ctx_switches,
+        extra: None,
+    },
+    SeriesSpec {
+        name: "ctx-involuntary",
+        label: "Involuntary switches",
+        unit: "/s",
+        decimals: 0,
+        color: ("GOLD", GOLD),
+        alert: None,
+        value: |data| data.involuntary_ctx_switches,
+        extra: None,
+    },
+    SeriesSpec {
+        name: "minor-faults",
+        label: "Minor faults",
+        unit: "/s",
+        decimals: 0,
+        color: ("CORAL", CORAL),
+        alert: None,
+        value: |data| data.minor_faults,
+        extra: None,
+    },
+    SeriesSpec {
+        name: "major-faults",
+        label: "Major faults",
+        unit: "/s",
+        decimals: 0,
+        color: ("OLIVE", OLIVE),
+        alert: None,
+        value: |data| data.major_faults,
+        extra: None,
+    },
+    SeriesSpec {
+        name: "user-cpu",
+        label: "User CPU",
+        unit: "%",
+        decimals: 2,
+        color: ("MINT", MINT),
+        alert: None,
+        value: |data| data.user_cpu,
+        extra: None,
+    },
+    SeriesSpec {
+        name: "system-cpu",
+        label: "System CPU",
+        unit: "%",
+        decimals: 2,
+        color: ("BROWN", BROWN),
+        alert: None,
+        value: |data| data.system_cpu,
+        extra: None,
+    },
+];
+
+// Position of the series in `REGISTRY` and in the histories of a target
+pub fn index(name: &str) -> Option<usize> {
+    REGISTRY.iter().position(|spec| spec.name == name)
+}
+
+// Parses a series name for `--metrics` and `--chart`
+pub fn parse(name: &str) -> Result<&'static SeriesSpec, String> {
+    let name = name.trim().to_lowercase();
+    let name = match name.as_str() {
+        "memory" | "mem" => "rss",
+        name => name,
+    };
+    index(name).map(|index| &REGISTRY[index]).ok_or_else(|| {
+        let names: Vec<&str> = REGISTRY.iter().map(|spec| spec.name).collect();
+        format!(
+            "unknown series '{}', expected one of: {}",
+            name,
+            names.join(", ")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::RED;
+
+    #[test]
+    fn colors_are_unique() {
+        for (index, spec) in REGISTRY.iter().enumerate() {
+            // Красный зарезервирован для сработавших алертов
+            assert_ne!(spec.color.1, RED, "{}", spec.name);
+            for other in &REGISTRY[index + 1..] {
+                assert_ne!(
+                    spec.color.1, other.color.1,
+                    "{} and {}",
+                    spec.name, other.name
+                );
+                assert_ne!(
+                    spec.color.0, other.color.0,
+                    "{} and {}",
+                    spec.name, other.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parses_series_names() {
+        assert_eq!(parse("CPU").unwrap().name, "cpu");
+        assert_eq!(parse(" mem ").unwrap().name, "rss");
+        assert_eq!(parse("memory").unwrap().name, "rss");
+        assert!(parse("gpu").is_err());
+    }
+}
@@ -0,0 +1,345 @@
+// Streaming statistics over the whole run, independent of the chart window.
+// Mean and variance use Welford's algorithm, percentiles come from a
+// log-bucketed histogram with ~1% relative precision, so memory stays bounded.
+
+const BUCKET_GROWTH: f64 = 1.01;
+
+pub struct StreamingStats {
+    count: u64,
+    mean: f64,
+    m2: f64,
+    min: f32,
+    max: f32,
+    // Bucket 0 holds zeros, bucket i holds values around BUCKET_GROWTH^(i-1)
+    histogram: Vec<u64>,
+}
+
+impl Default for StreamingStats {
+    fn default() -> Self {
+        StreamingStats::new()
+    }
+}
+
+impl StreamingStats {
+    pub fn new() -> Self {
+        StreamingStats {
+            count: 0,
+            mean: 0.,
+            m2: 0.,
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY,
+            histogram: Vec::new(),
+        }
+    }
+
+    fn bucket(value: f32) -> usize {
+        if value <= 0. {
+            return 0;
+        }
+        // Сдвигаем на 1, чтобы значения меньше 1 тоже попадали в отдельные корзины
+        ((value as f64 + 1.).ln() / BUCKET_GROWTH.ln()) as usize + 1
+    }
+
+    fn bucket_value(bucket: usize) -> f32 {
+        if bucket == 0 {
+            return 0.;
+        }
+        let low = BUCKET_GROWTH.powi(bucket as i32 - 1) - 1.;
+        let high = BUCKET_GROWTH.powi(bucket as i32) - 1.;
+        ((low + high) / 2.) as f32
+    }
+
+    pub fn push(&mut self, value: f32) {
+        self.count += 1;
+        let delta = value as f64 - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value as f64 - self.mean);
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+
+        let bucket = Self::bucket(value);
+        if bucket >= self.histogram.len() {
+            self.histogram.resize(bucket + 1, 0);
+        }
+        self.histogram[bucket] += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> f32 {
+        if self.count == 0 {
+            0.
+        } else {
+            self.min
+        }
+    }
+
+    pub fn max(&self) -> f32 {
+        if self.count == 0 {
+            0.
+        } else {
+            self.max
+        }
+    }
+
+    pub fn mean(&self) -> f32 {
+        self.mean as f32
+    }
+
+    pub fn std_dev(&self) -> f32 {
+        if self.count < 2 {
+            return 0.;
+        }
+        (self.m2 / (self.count - 1) as f64).sqrt() as f32
+    }
+
+    // Percentile in 0..=100, clamped to the exact min/max
+    pub fn percentile(&self, percentile: f32) -> f32 {
+        if self.count == 0 {
+            return 0.;
+        }
+        let rank = ((percentile / 100.) as f64 * self.count as f64)
+            .ceil()
+            .max(1.) as u64;
+        let mut seen = 0;
+        for (bucket, &count) in self.histogram.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Self::bucket_value(bucket).clamp(self.min, self.max);
+            }
+        }
+        self.max
+    }
+}
+
+// Mean and sample variance of a few values, e.g. the results of separate runs
+pub fn mean_variance(values: &[f64]) -> (f64, f64) {
+    if values.is_empty() {
+        return (0., 0.);
+    }
+    let count = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / count;
+    if values.len() < 2 {
+        return (mean, 0.);
+    }
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / (count - 1.);
+    (mean, variance)
+}
+
+// Two-sided p-value of Welch's t-test for a difference between the means of
+// `a` and `b`, None if either side has fewer than two values
+pub fn welch_t_test(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() < 2 || b.len() < 2 {
+        return None;
+    }
+    let (mean_a, variance_a) = mean_variance(a);
+    let (mean_b, variance_b) = mean_variance(b);
+    let error_a = variance_a / a.len() as f64;
+    let error_b = variance_b / b.len() as f64;
+    let error = error_a + error_b;
+    if error == 0. {
+        // Без разброса любая разница средних значима
+        return Some(if mean_a == mean_b { 1. } else { 0. });
+    }
+    let t = (mean_a - mean_b) / error.sqrt();
+    // Степени свободы по формуле Уэлча-Саттертуэйта
+    let df = error.powi(2)
+        / (error_a.powi(2) / (a.len() - 1) as f64 + error_b.powi(2) / (b.len() - 1) as f64);
+    Some(incomplete_beta(df / 2., 0.5, df / (df + t * t)))
+}
+
+// Regularized incomplete beta function I_x(a, b)
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0. {
+        return 0.;
+    }
+    if x >= 1. {
+        return 1.;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1. - x).ln()).exp();
+    // Цепная дробь быстро сходится только слева от среднего, иначе через симметрию
+    if x < (a + 1.) / (a + b + 2.) {
+        front * beta_fraction(a, b, x) / a
+    } else {
+        1. - front * beta_fraction(b, a, 1. - x) / b
+    }
+}
+
+// Continued fraction of the incomplete beta function, evaluated with Lentz's method
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let clamp = |value: f64| if value.abs() < TINY { TINY } else { value };
+
+    let mut c = 1.;
+    let mut d = 1. / clamp(1. - (a + b) * x / (a + 1.));
+    let mut result = d;
+    for m in 1..300 {
+        let m = m as f64;
+        let even = m * (b - m) * x / ((a + 2. * m - 1.) * (a + 2. * m));
+        d = 1. / clamp(1. + even * d);
+        c = clamp(1. + even / c);
+        result *= d * c;
+
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2. * m) * (a + 2. * m + 1.));
+        d = 1. / clamp(1. + odd * d);
+        c = clamp(1. + odd / c);
+        let delta = d * c;
+        result *= delta;
+        if (delta - 1.).abs() < 1e-12 {
+            break;
+        }
+    }
+    result
+}
+
+// ln Γ(x) for x > 0, Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.001_208_650_973_866_179,
+        -0.000_005_395_239_384_953,
+    ];
+    let base = x + 5.5;
+    let mut series = 1.000_000_000_190_015;
+    for (index, coefficient) in COEFFICIENTS.iter().enumerate() {
+        series += coefficient / (x + 1. + index as f64);
+    }
+    (x + 0.5) * base.ln() - base + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{} is not within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    #[test]
+    fn empty_stats_are_zero() {
+        let stats = StreamingStats::new();
+        assert_eq!(stats.count(), 0);
+        assert_eq!(stats.min(), 0.);
+        assert_eq!(stats.max(), 0.);
+        assert_eq!(stats.mean(), 0.);
+        assert_eq!(stats.std_dev(), 0.);
+        assert_eq!(stats.percentile(50.), 0.);
+    }
+
+    #[test]
+    fn streaming_mean_and_variance() {
+        let mut stats = StreamingStats::new();
+        for value in 1..=100 {
+            stats.push(value as f32);
+        }
+        assert_eq!(stats.count(), 100);
+        assert_eq!(stats.min(), 1.);
+        assert_eq!(stats.max(), 100.);
+        assert_close(stats.mean() as f64, 50.5, 1e-4);
+        // Выборочная дисперсия 1..=100 равна 841.67
+        assert_close(stats.std_dev() as f64, 841.666_666_7_f64.sqrt(), 1e-3);
+
+        // Welford не теряет точность на большом смещении
+        let mut stats = StreamingStats::new();
+        for value in [1_000_001., 1_000_002., 1_000_003.] {
+            stats.push(value);
+        }
+        assert_close(stats.std_dev() as f64, 1., 1e-6);
+
+        let mut stats = StreamingStats::new();
+        stats.push(5.);
+        assert_eq!(stats.std_dev(), 0.);
+    }
+
+    #[test]
+    fn percentiles_within_bucket_precision() {
+        let mut stats = StreamingStats::new();
+        for value in 1..=1000 {
+            stats.push(value as f32);
+        }
+        for (percentile, expected) in [(50., 500.), (90., 900.), (99., 990.)] {
+            let actual = stats.percentile(percentile) as f64;
+            assert_close(actual, expected, expected * 0.01);
+        }
+        // Края не выходят за точные min и max
+        for percentile in [0., 100.] {
+            let value = stats.percentile(percentile);
+            assert!((1. ..=1000.).contains(&value));
+        }
+        assert_close(stats.percentile(0.) as f64, 1., 0.01);
+        assert_close(stats.percentile(100.) as f64, 1000., 10.);
+
+        let mut stats = StreamingStats::new();
+        for value in [0., 0., 0., 7.] {
+            stats.push(value);
+        }
+        assert_eq!(stats.percentile(50.), 0.);
+        assert_close(stats.percentile(100.) as f64, 7., 0.07);
+    }
+
+    #[test]
+    fn mean_variance_of_runs() {
+        assert_eq!(mean_variance(&[]), (0., 0.));
+        assert_eq!(mean_variance(&[3.]), (3., 0.));
+        let (mean, variance) = mean_variance(&[2., 4., 4., 4., 5., 5., 7., 9.]);
+        assert_close(mean, 5., 1e-12);
+        assert_close(variance, 32. / 7., 1e-12);
+    }
+
+    // Expected p-values are those of scipy.stats.ttest_ind(a, b, equal_var=False)
+    #[test]
+    fn welch_p_values() {
+        let p = welch_t_test(&[1., 2., 3., 4., 5.], &[2., 3., 4., 5., 6.]).unwrap();
+        assert_close(p, 0.346_593_507_087_334_1, 1e-9);
+
+        let a = [
+            27.5, 21.0, 19.0, 23.6, 17.0, 17.9, 16.9, 20.1, 21.9, 22.6, 23.1, 19.6, 19.0, 21.7,
+            21.4,
+        ];
+        let b = [
+            27.1, 22.0, 20.8, 23.4, 23.4, 23.5, 25.8, 22.0, 24.8, 20.2, 21.9, 22.1, 22.9, 20.5,
+            24.4,
+        ];
+        assert_close(welch_t_test(&a, &b).unwrap(), 0.021_378_001_462_867, 1e-9);
+
+        let p = welch_t_test(&[10., 11., 12.], &[20., 21., 22.5]).unwrap();
+        assert_close(p, 0.000_512_716_563_760_262, 1e-10);
+
+        // Разные размеры и дисперсии, дробные степени свободы
+        let p = welch_t_test(&[3.1, 2.9, 3.4, 3.0], &[3.3, 3.6, 3.2, 3.9, 3.5, 3.4]).unwrap();
+        assert_close(p, 0.035_029_584_993_391_5, 1e-9);
+
+        // Тест симметричен
+        let p = welch_t_test(&[2., 3., 4., 5., 6.], &[1., 2., 3., 4., 5.]).unwrap();
+        assert_close(p, 0.346_593_507_087_334_1, 1e-9);
+    }
+
+    #[test]
+    fn welch_without_variance() {
+        assert_eq!(welch_t_test(&[1., 1., 1.], &[1., 1.]), Some(1.));
+        assert_eq!(welch_t_test(&[1., 1., 1.], &[2., 2.]), Some(0.));
+    }
+
+    #[test]
+    fn welch_needs_two_values_per_side() {
+        assert_eq!(welch_t_test(&[], &[1., 2.]), None);
+        assert_eq!(welch_t_test(&[1.], &[1., 2.]), None);
+        assert_eq!(welch_t_test(&[1., 2.], &[3.]), None);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::target::Target;
+use chrono::{DateTime, Local};
+use monit_tool::stats::StreamingStats;
+use std::process::ExitStatus;
+
+// p50, p90 and p99
+fn percentiles(stats: &StreamingStats) -> [f32; 3] {
+    [
+        stats.percentile(50.),
+        stats.percentile(90.),
+        stats.percentile(99.),
+    ]
+}
+
+// Final statistics of one target
+pub struct Summary {
+    pub label: String,
+    pub name: String,
+    pub restart_count: u32,
+    pub exits: Vec<(DateTime<Local>, ExitStatus)>,
+    pub gave_up: Option<String>,
+    pub has_command: bool,
+    pub samples: u64,
+    pub min_cpu_usage: f32,
+    pub max_cpu_usage: f32,
+    pub avg_cpu_usage: f32,
+    pub std_dev_cpu_usage: f32,
+    pub percentiles_cpu_usage: [f32; 3],
+    pub min_memory_usage: f32,
+    pub max_memory_usage: f32,
+    pub avg_memory_usage: f32,
+    pub std_dev_memory_usage: f32,
+    pub percentiles_memory_usage: [f32; 3],
+    pub total_disk_write: f32,
+    pub total_disk_read: f32,
+    pub marks: Vec<(DateTime<Local>, String)>,
+    pub avg_disk_write_rate: f32,
+    pub avg_disk_read_rate: f32,
+    pub max_disk_write_rate: f32,
+    pub max_disk_read_rate: f32,
+}
+
+impl Summary {
+    pub fn from_target(target: &Target) -> Self {
+        let name = target
+            .command
+            .clone()
+            .or_else(|| target.last.as_ref().map(|data| data.name.clone()))
+            .unwrap_or_default();
+
+        Summary {
+            label: target.label(),
+            name,
+            restart_count: target.restart_count,
+            exits: target.exits.clone(),
+            gave_up: target.gave_up.clone(),
+            has_command: target.command.is_some(),
+            samples: target.cpu_stats.count(),
+            min_cpu_usage: target.cpu_stats.min(),
+            max_cpu_usage: target.cpu_stats.max(),
+            avg_cpu_usage: target.cpu_stats.mean(),
+            std_dev_cpu_usage: target.cpu_stats.std_dev(),
+            percentiles_cpu_usage: percentiles(&target.cpu_stats),
+            min_memory_usage: target.memory_stats.min(),
+            max_memory_usage: target.memory_stats.max(),
+            avg_memory_usage: target.memory_stats.mean(),
+            std_dev_memory_usage: target.memory_stats.std_dev(),
+            percentiles_memory_usage: percentiles(&target.memory_stats),
+            marks: target.marks.clone(),
+            total_disk_write: target.total_written_bytes,
+            total_disk_read: target.total_read_bytes,
+            avg_disk_write_rate: target.disk_write_stats.mean(),
+            avg_disk_read_rate: target.disk_read_stats.mean(),
+            max_disk_write_rate: target.disk_write_stats.max(),
+            max_disk_read_rate: target.disk_read_stats.max(),
+        }
+    }
+
+    pub fn print(&self, watch: bool) {
+        println!("\n[{}] {}", self.label, self.name);
+        if (watch && self.has_command) || self.restart_count > 0 {
+            println!("Process restarts: {}", self.restart_count);
+        }
+        if let Some(ref reason) = self.gave_up {
+            println!("Gave up restarting: {}", reason);
+        }
+        if !self.exits.is_empty() {
+            println!("Exits: {}", self.exits.len());
+            for (time, status) in &self.exits {
+                println!("  {}: {}", time.format("%Y-%m-%d %H:%M:%S"), status);
+            }
+        }
+        println!("Samples: {}", self.samples);
+        println!("Min Memory Usage: {:.2} MB", self.min_memory_usage);
+        println!("Min CPU Usage: {:.2}%", self.min_cpu_usage);
+        println!("Max CPU Usage: {:.2}%", self.max_cpu_usage);
+        println!("Average CPU Usage: {:.2}%", self.avg_cpu_usage);
+        println!(
+            "CPU Usage p50/p90/p99: {:.2}% / {:.2}% / {:.2}%",
+            self.percentiles_cpu_usage[0],
+            self.percentiles_cpu_usage[1],
+            self.percentiles_cpu_usage[2]
+        );
+        println!("CPU Usage Std Dev: {:.2}%", self.std_dev_cpu_usage);
+        println!("Max Memory Usage: {:.2} MB", self.max_memory_usage);
+        println!("Average Memory Usage: {:.2} MB", self.avg_memory_usage);
+        println!(
+            "Memory Usage p50/p90/p99: {:.2} MB / {:.2} MB / {:.2} MB",
+            self.percentiles_memory_usage[0],
+            self.percentiles_memory_usage[1],
+            self.percentiles_memory_usage[2]
+        );
+        println!("Memory Usage Std Dev: {:.2} MB", self.std_dev_memory_usage);
+        println!(
+            "Total Disk Write: {:.2} MB (Average: {:.2} MB/s, Max: {:.2} MB/s)",
+            self.total_disk_write, self.avg_disk_write_rate, self.max_disk_write_rate
+        );
+        println!(
+            "Total Disk Read: {:.2} MB (Average: {:.2} MB/s, Max: {:.2} MB/s)",
+            self.total_disk_read, self.avg_disk_read_rate, self.max_disk_read_rate
+        );
+        if !self.marks.is_empty() {
+            println!("Timeline marks: {}", self.marks.len());
+            // Показываем только последние отметки
+            let skip = self.marks.len().saturating_sub(10);
+            for (time, line) in self.marks.iter().skip(skip) {
+                println!("  {}: {}", time.format("%H:%M:%S"), line);
+            }
+        }
+    }
+}
@@ -0,0 +1,490 @@
+use crate::output_capture::OutputCapture;
+use crate::restart_policy::RestartPolicy;
+use crate::{refresh_system, start_process, GREEN, RED};
+use chrono::{DateTime, Local};
+use monit_tool::alert::{Alert, AlertRule, Metric};
+use monit_tool::data_collector::{DataCollector, ProcessData, ProcessNode};
+use monit_tool::history::History;
+use monit_tool::process_group;
+use monit_tool::selector::Selector;
+use monit_tool::stats::StreamingStats;
+use rgb::RGB8;
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::process::{Child, ExitStatus};
+use std::thread;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessStatus, Signal, System};
+
+// How often the process group is checked while waiting for it to exit
+const STOP_POLL: Duration = Duration::from_millis(50);
+// How long SIGKILLed processes get to disappear before survivors are reported
+const KILL_WAIT: Duration = Duration::from_secs(1);
+
+// Result of checking a target's own child process
+pub enum ChildState {
+    Running,
+    Restarted,
+    // Exited, the restart is delayed by the backoff
+    Waiting,
+    Exited,
+}
+
+// Memory history of one descendant, used to chart the top-N children
+pub struct ChildSeries {
+    pub pid: Pid,
+    pub name: String,
+    pub cpu_usage: f32,
+    pub memory_usage: f32,
+    pub memory_data: History,
+}
+
+pub struct Target {
+    // Command used to (re)start the process, if the target was launched by us
+    pub command: Option<String>,
+    pub child: Option<Child>,
+    pub pid: Pid,
+    // How to find the process again if it was not started by us
+    pub selector: Option<Selector>,
+    // cgroup v2 directory whose processes are monitored together
+    pub cgroup: Option<PathBuf>,
+
+    // Captured stdout/stderr of the command and the times `--mark` patterns matched
+    pub output: Option<OutputCapture>,
+    pub marks: Vec<(DateTime<Local>, String)>,
+
+    // Per-target series and the values derived from them
+    pub data_collector: DataCollector,
+    pub last: Option<ProcessData>,
+    // Last non-empty process tree, used to find processes that survived a stop
+    pub last_tree: Vec<ProcessNode>,
+    // Automatic restarts of watch mode, manual ones are not limited by the policy
+    pub restart_count: u32,
+    // Last exit status of the child process and all exits with their time
+    pub exit_status: Option<ExitStatus>,
+    pub exits: Vec<(DateTime<Local>, ExitStatus)>,
+
+    // Restart policy of watch mode and its state
+    pub restart_policy: RestartPolicy,
+    pub started: Instant,
+    pub restart_at: Option<Instant>,
+    pub restart_times: Vec<Instant>,
+    pub backoff_attempt: u32,
+    // Why the command is not restarted any more
+    pub gave_up: Option<String>,
+    pub tick: usize,
+    pub max: f32,
+    pub memory_usage_min: f32,
+    pub memory_color: RGB8,
+
+    // Statistics over the whole run, not only the chart window
+    pub cpu_stats: StreamingStats,
+    pub memory_stats: StreamingStats,
+    pub disk_read_stats: StreamingStats,
+    pub disk_write_stats: StreamingStats,
+    // Lifetime disk I/O of the tree, MB
+    pub total_read_bytes: f32,
+    pub total_written_bytes: f32,
+
+    // Per-descendant series, only kept when `track_children` is set
+    pub track_children: bool,
+    pub child_series: Vec<ChildSeries>,
+
+    // Alert rules with their per-target state
+    pub alerts: Vec<Alert>,
+
+    // Signal sent to the process group on stop and the time it gets before SIGKILL
+    pub stop_signal: Signal,
+    pub grace_period: Duration,
+
+    // Set once the process is gone and will not be watched any more
+    pub finished: bool,
+}
+
+impl Target {
+    pub fn new(pid: Pid, command: Option<String>, child: Option<Child>) -> Self {
+        Target {
+            command,
+            child,
+            pid,
+            selector: None,
+            cgroup: None,
+            output: None,
+            marks: Vec::new(),
+            data_collector: DataCollector::new(),
+            last: None,
+            last_tree: Vec::new(),
+            restart_count: 0,
+            exit_status: None,
+            exits: Vec::new(),
+            restart_policy: RestartPolicy::default(),
+            started: Instant::now(),
+            restart_at: None,
+            restart_times: Vec::new(),
+            backoff_attempt: 0,
+            gave_up: None,
+            tick: 0,
+            max: 0.,
+            memory_usage_min: 0.,
+            memory_color: GREEN,
+            cpu_stats: StreamingStats::new(),
+            memory_stats: StreamingStats::new(),
+            disk_read_stats: StreamingStats::new(),
+            disk_write_stats: StreamingStats::new(),
+            total_read_bytes: 0.,
+            total_written_bytes: 0.,
+            track_children: false,
+            child_series: Vec::new(),
+            alerts: Vec::new(),
+            stop_signal: Signal::Term,
+            grace_period: Duration::from_secs(5),
+            finished: false,
+        }
+    }
+
+    pub fn spawn(command: &str, workdir: &str, output: OutputCapture) -> Result<Self, io::Error> {
+        let mut child = start_process(command, workdir)?;
+        output.attach(&mut child);
+        let pid = Pid::from_u32(child.id());
+        let mut target = Target::new(pid, Some(command.to_string()), Some(child));
+        target.output = Some(output);
+        Ok(target)
+    }
+
+    fn start(&mut self, command: &str, workdir: &str, automatic: bool) -> Result<(), io::Error> {
+        let mut child = start_process(command, workdir)?;
+        if let Some(ref output) = self.output {
+            output.attach(&mut child);
+        }
+        self.pid = Pid::from_u32(child.id());
+        self.child = Some(child);
+        self.started = Instant::now();
+        self.mark_restart(automatic);
+        Ok(())
+    }
+
+    // Checks whether the child exited and restarts it in watch mode
+    // according to the restart policy
+    pub fn poll_child(&mut self, watch: bool, workdir: &str) -> Result<ChildState, io::Error> {
+        if let Some(restart_at) = self.restart_at {
+            if Instant::now() < restart_at {
+                return Ok(ChildState::Waiting);
+            }
+            return self.restart_now(workdir);
+        }
+
+        let Some(ref mut child_process) = self.child else {
+            return Ok(ChildState::Running);
+        };
+        let Ok(Some(status)) = child_process.try_wait() else {
+            return Ok(ChildState::Running);
+        };
+        println!("Process {} exited with status: {}", self.pid, status);
+        self.exit_status = Some(status);
+        self.exits.push((Local::now(), status));
+
+        if !watch || self.command.is_none() || !self.restart_policy.wants_restart(status) {
+            return Ok(ChildState::Exited);
+        }
+
+        let now = Instant::now();
+        // Процесс проработал дольше максимальной задержки, начинаем backoff заново
+        if now.duration_since(self.started) >= self.restart_policy.max_backoff {
+            self.backoff_attempt = 0;
+        }
+        if let Err(reason) =
+            self.restart_policy
+                .check(self.restart_count, &mut self.restart_times, now)
+        {
+            println!("Not restarting process {}: {}", self.pid, reason);
+            self.gave_up = Some(reason);
+            return Ok(ChildState::Exited);
+        }
+
+        let delay = self.restart_policy.delay(self.backoff_attempt);
+        self.backoff_attempt += 1;
+        if delay.is_zero() {
+            return self.restart_now(workdir);
+        }
+        println!("Restarting process in {:.1}s...", delay.as_secs_f32());
+        self.restart_at = Some(now + delay);
+        Ok(ChildState::Waiting)
+    }
+
+    fn restart_now(&mut self, workdir: &str) -> Result<ChildState, io::Error> {
+        self.restart_at = None;
+        let Some(command) = self.command.clone() else {
+            return Ok(ChildState::Exited);
+        };
+        // Потомки прошлого запуска могли остаться в его группе процессов
+        if let Some(mut child_process) = self.child.take() {
+            if self.stop_group(&mut child_process) {
+                println!(
+                    "Stopped the remaining processes of group {}",
+                    child_process.id()
+                );
+            }
+        }
+        // Перезапускаем процесс, если включен режим watch и есть команда
+        println!("Restarting process...");
+        self.start(&command, workdir, true)?;
+        Ok(ChildState::Restarted)
+    }
+
+    // Collects a new sample and pushes it into the target's series
+    pub fn sample(&mut self, system: &System) -> bool {
+        let time = Local::now();
+        let process_data = match self.cgroup {
+            Some(ref path) => self.data_collector.get_cgroup_data(system, path),
+            None => self.data_collector.get_process_data(system, self.pid),
+        };
+        let Some(process_data) = process_data else {
+            self.last = None;
+            return false;
+        };
+        self.push(process_data, time);
+        true
+    }
+
+    // Pushes an already collected sample (live or replayed) into the series
+    pub fn push(&mut self, process_data: ProcessData, time: DateTime<Local>) {
+        if self.memory_usage_min == 0. && process_data.memory_usage > 0. {
+            self.memory_usage_min = process_data.memory_usage;
+        }
+        if process_data.memory_usage > self.memory_usage_min * 2. {
+            self.memory_color = RED;
+        } else {
+            self.memory_color = GREEN;
+        }
+
+        if process_data.memory_usage > self.max {
+            self.max = process_data.memory_usage;
+        }
+
+        // Обновление статистики CPU и памяти
+        self.cpu_stats.push(process_data.cpu_usage);
+        self.memory_stats.push(process_data.memory_usage);
+        self.disk_read_stats.push(process_data.disk_read_rate);
+        self.disk_write_stats.push(process_data.disk_write_rate);
+        self.total_read_bytes = self.total_read_bytes.max(process_data.total_read_bytes);
+        self.total_written_bytes = self
+            .total_written_bytes
+            .max(process_data.total_written_bytes);
+
+        self.data_collector
+            .set_sample_time(time)
+            .update_series(&process_data);
+
+        if self.track_children {
+            self.update_child_series(&process_data);
+        }
+
+        if !process_data.tree.is_empty() {
+            self.last_tree = process_data.tree.clone();
+        }
+        self.last = Some(process_data);
+        self.tick += 1;
+    }
+
+    fn update_child_series(&mut self, process_data: &ProcessData) {
+        let elapsed = self.data_collector.elapsed();
+        // Деревья бывают в тысячи процессов, поэтому ищем узлы по PID, а не перебором
+        let mut nodes: HashMap<Pid, &ProcessNode> = process_data
+            .tree
+            .iter()
+            .map(|node| (node.pid, node))
+            .collect();
+
+        for series in self.child_series.iter_mut() {
+            let node = nodes.remove(&series.pid);
+            series.cpu_usage = node.map_or(0., |node| node.cpu_usage);
+            series.memory_usage = node.map_or(0., |node| node.memory_usage);
+            series.memory_data.push(elapsed, series.memory_usage);
+        }
+
+        // Корень дерева уже показан на основном графике
+        for node in process_data.tree.iter().skip(1) {
+            if !nodes.contains_key(&node.pid) {
+                continue;
+            }
+            let mut memory_data = History::new(self.data_collector.history());
+            memory_data.push(elapsed, node.memory_usage);
+            self.child_series.push(ChildSeries {
+                pid: node.pid,
+                name: node.name.clone(),
+                cpu_usage: node.cpu_usage,
+                memory_usage: node.memory_usage,
+                memory_data,
+            });
+        }
+
+        // Убираем завершившиеся процессы, которых уже нет среди последних замеров
+        self.child_series
+            .retain(|series| series.memory_data.recent_max() > 0.);
+    }
+
+    // Age of the marks in seconds relative to the latest sample, the X of the chart
+    pub fn mark_positions(&self) -> Vec<f32> {
+        let Some(last_sample) = self.data_collector.last_sample() else {
+            return Vec::new();
+        };
+        self.marks
+            .iter()
+            // Отметка новее последнего замера (перезапуск) стоит на нём
+            .map(|&(time, _)| ((last_sample - time).num_milliseconds() as f32 / 1000.).max(0.))
+            .collect()
+    }
+
+    // Children with the highest current memory usage
+    pub fn top_children(&self, count: usize) -> Vec<&ChildSeries> {
+        let mut children: Vec<&ChildSeries> = self
+            .child_series
+            .iter()
+            .filter(|series| series.memory_usage > 0.)
+            .collect();
+        children.sort_by(|a, b| b.memory_usage.total_cmp(&a.memory_usage));
+        children.truncate(count);
+        children
+    }
+
+    // Evaluates the alert rules against the last sample and returns the rules that fired
+    pub fn check_alerts(&mut self, now: Instant) -> Vec<AlertRule> {
+        let Some(ref process_data) = self.last else {
+            return Vec::new();
+        };
+
+        self.alerts
+            .iter_mut()
+            .filter_map(|alert| alert.check(process_data, now).then(|| alert.rule.clone()))
+            .collect()
+    }
+
+    pub fn alert_active(&self, metric: Metric) -> bool {
+        self.alerts
+            .iter()
+            .any(|alert| alert.active && alert.rule.metric == metric)
+    }
+
+    // How the target is named in messages, the summary and the exports. A
+    // cgroup has no root process, its processes come and go, so its path is used
+    pub fn label(&self) -> String {
+        match self.cgroup {
+            Some(ref path) => format!("cgroup {}", path.display()),
+            None => format!("PID {}", self.pid),
+        }
+    }
+
+    // Tries to find the process again after it disappeared (watch mode)
+    pub fn reattach(&mut self, system: &System) -> Option<Pid> {
+        let new_pid = self.selector.as_ref()?.resolve(system)?;
+        self.pid = new_pid;
+        self.mark_restart(true);
+        Some(new_pid)
+    }
+
+    // Marks the restart on the timeline, automatic ones are also counted
+    fn mark_restart(&mut self, automatic: bool) {
+        let kind = if automatic {
+            self.restart_count += 1;
+            "restart"
+        } else {
+            "manual restart"
+        };
+        self.marks
+            .push((Local::now(), format!("[{}] PID {}", kind, self.pid)));
+    }
+
+    // Stops the process group of the child and starts the command again
+    pub fn restart(&mut self, system: &mut System, workdir: &str) -> Result<(), io::Error> {
+        let Some(command) = self.command.clone() else {
+            return Ok(());
+        };
+        self.terminate(system);
+        self.restart_at = None;
+        self.start(&command, workdir, false)?;
+        self.finished = false;
+        Ok(())
+    }
+
+    // Stamps the new output pattern matches with the current time
+    pub fn collect_marks(&mut self) -> Vec<String> {
+        let Some(ref output) = self.output else {
+            return Vec::new();
+        };
+        let lines: Vec<String> = output
+            .take_matches()
+            .into_iter()
+            .map(|output_match| format!("[{}] {}", output_match.pattern, output_match.line))
+            .collect();
+        let now = Local::now();
+        for line in &lines {
+            self.marks.push((now, line.clone()));
+        }
+        lines
+    }
+
+    // Stops the whole process group of the child if it was started by us:
+    // `stop_signal` first, SIGKILL after `grace_period`
+    pub fn terminate(&mut self, system: &mut System) {
+        let Some(mut child_process) = self.child.take() else {
+            return;
+        };
+        if !self.stop_group(&mut child_process) {
+            // Нет группы процессов (Windows) или она уже пуста
+            let _ = child_process.kill();
+        }
+        let _ = child_process.wait();
+        println!("Process with PID {} has been terminated.", self.pid);
+
+        // Процессы, ушедшие в другую группу или сессию, сигнал не получили
+        refresh_system(system);
+        let survivors: Vec<&ProcessNode> = self
+            .last_tree
+            .iter()
+            .filter(|node| {
+                system
+                    .process(node.pid)
+                    .is_some_and(|process| process.status() != ProcessStatus::Zombie)
+            })
+            .collect();
+        for node in survivors {
+            println!(
+                "Process {} ({}) survived the termination",
+                node.pid, node.name
+            );
+        }
+        self.last_tree.clear();
+    }
+
+    // Sends `stop_signal` to the process group of the child and SIGKILL to
+    // whatever is left after `grace_period`. False if there was no group to signal
+    fn stop_group(&self, child_process: &mut Child) -> bool {
+        let leader = Pid::from_u32(child_process.id());
+        if !process_group::signal(leader, self.stop_signal) {
+            return false;
+        }
+        let deadline = Instant::now() + self.grace_period;
+        while Self::group_running(child_process, leader) && Instant::now() < deadline {
+            thread::sleep(STOP_POLL);
+        }
+        if Self::group_running(child_process, leader) {
+            println!(
+                "Process group {} is still running after {:?}, sending SIGKILL",
+                leader, self.grace_period
+            );
+            process_group::signal(leader, Signal::Kill);
+            let deadline = Instant::now() + KILL_WAIT;
+            while Self::group_running(child_process, leader) && Instant::now() < deadline {
+                thread::sleep(STOP_POLL);
+            }
+        }
+        true
+    }
+
+    // Reaps the child if it exited, so its zombie does not keep the group alive
+    fn group_running(child_process: &mut Child, leader: Pid) -> bool {
+        let _ = child_process.try_wait();
+        process_group::alive(leader)
+    }
+}
@@ -0,0 +1,140 @@
+use std::time::Duration;
+use sysinfo::Signal;
+
+// Parses durations like `500ms`, `10s`, `5m` or `1h`, plain numbers are seconds
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, multiplier) = if let Some(number) = value.strip_suffix("ms") {
+        (number, 0.001)
+    } else if let Some(number) = value.strip_suffix('s') {
+        (number, 1.)
+    } else if let Some(number) = value.strip_suffix('m') {
+        (number, 60.)
+    } else if let Some(number) = value.strip_suffix('h') {
+        (number, 3600.)
+    } else {
+        (value, 1.)
+    };
+    let number: f32 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", value))?;
+    if number < 0. {
+        return Err(format!("negative duration '{}'", value));
+    }
+    // NaN и слишком большие значения не помещаются в Duration
+    Duration::try_from_secs_f32(number * multiplier)
+        .map_err(|_| format!("invalid duration '{}'", value))
+}
+
+// Parses sizes like `512KB`, `500MB` or `2GB` into MB, plain numbers are MB
+pub fn parse_size(value: &str) -> Result<f32, String> {
+    let value = value.trim().to_uppercase();
+    let (number, multiplier) = if let Some(number) = value.strip_suffix("GB") {
+        (number, 1024.)
+    } else if let Some(number) = value.strip_suffix("MB") {
+        (number, 1.)
+    } else if let Some(number) = value.strip_suffix("KB") {
+        (number, 1. / 1024.)
+    } else {
+        (value.as_str(), 1.)
+    };
+    let number: f32 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", value))?;
+    // Парсер f32 принимает `NAN` и `INF` в любом регистре
+    if !number.is_finite() || number < 0. {
+        return Err(format!("invalid size '{}'", value));
+    }
+    Ok(number * multiplier)
+}
+
+// Parses percentages like `80` or `150%` (per-core CPU usage can exceed 100)
+pub fn parse_percent(value: &str) -> Result<f32, String> {
+    let value = value.trim();
+    let number: f32 = value
+        .strip_suffix('%')
+        .unwrap_or(value)
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid percentage '{}'", value))?;
+    if !number.is_finite() || number < 0. {
+        return Err(format!("invalid percentage '{}'", value));
+    }
+    Ok(number)
+}
+
+// Parses signal names like `TERM`, `SIGINT` or `usr1`
+pub fn parse_signal(name: &str) -> Result<Signal, String> {
+    let name = name.trim().to_uppercase();
+    let signal = match name.strip_prefix("SIG").unwrap_or(&name) {
+        "TERM" => Signal::Term,
+        "KILL" => Signal::Kill,
+        "INT" => Signal::Interrupt,
+        "HUP" => Signal::Hangup,
+        "QUIT" => Signal::Quit,
+        "USR1" => Signal::User1,
+        "USR2" => Signal::User2,
+        "STOP" => Signal::Stop,
+        "CONT" => Signal::Continue,
+        _ => return Err(format!("unknown signal '{}'", name)),
+    };
+    Ok(signal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations_with_units() {
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn plain_numbers_are_seconds() {
+        assert_eq!(parse_duration("10"), Ok(Duration::from_secs(10)));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("s").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        assert!(parse_duration("-5s").is_err());
+        assert!(parse_duration("nan").is_err());
+        assert!(parse_duration("NaNs").is_err());
+        assert!(parse_duration("1e30s").is_err());
+        assert!(parse_duration("inf").is_err());
+        assert!(parse_duration("5x").is_err());
+    }
+
+    #[test]
+    fn parses_sizes_with_units() {
+        assert_eq!(parse_size("500"), Ok(500.));
+        assert_eq!(parse_size("2gb"), Ok(2048.));
+        assert_eq!(parse_size("512KB"), Ok(0.5));
+        assert_eq!(parse_size("0MB"), Ok(0.));
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        for value in [
+            "nan", "NaNMB", "inf", "-infGB", "-1", "-0.5MB", "1e39", "MB", "5TB",
+        ] {
+            assert!(parse_size(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn parses_percentages() {
+        assert_eq!(parse_percent("80"), Ok(80.));
+        assert_eq!(parse_percent("150%"), Ok(150.));
+        for value in ["nan", "inf", "-1", "-5%", "%", "x"] {
+            assert!(parse_percent(value).is_err(), "{}", value);
+        }
+    }
+}
@@ -0,0 +1,25 @@
+#![cfg(unix)]
+
+use monit_tool::ProcessManager;
+use std::process::Command;
+
+#[test]
+fn lists_processes_matching_the_filter() {
+    let mut child = Command::new("sleep").arg("7.25").spawn().unwrap();
+
+    let processes = ProcessManager::new().get_filtered_processes("SLEEP 7.25");
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    // Фильтр не зависит от регистра и ищет и в имени, и в командной строке
+    let item = processes
+        .iter()
+        .find(|item| item.pid == child.id())
+        .expect("started process is not listed");
+    assert_eq!(item.name, "sleep - sleep 7.25");
+    // Строка собирается во время выполнения, чтобы не встретиться в чужой командной строке
+    let filter = format!("no-process-{}-{}", "named", child.id());
+    assert!(ProcessManager::new()
+        .get_filtered_processes(&filter)
+        .is_empty());
+}
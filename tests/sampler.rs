@@ -0,0 +1,34 @@
+#![cfg(unix)]
+
+use monit_tool::history::Downsample;
+use monit_tool::Sampler;
+use std::process::Command;
+use std::time::{Duration, Instant};
+use sysinfo::Pid;
+
+#[test]
+fn samples_a_process_until_it_exits() {
+    let mut child = Command::new("sleep").arg("1").spawn().unwrap();
+    let mut sampler = Sampler::new(Pid::from_u32(child.id()));
+    sampler.set_interval(Duration::from_millis(100));
+
+    let started = Instant::now();
+    let mut samples = 0;
+    for process_data in sampler.by_ref() {
+        assert!(process_data.memory_usage > 0.);
+        samples += 1;
+        assert!(
+            started.elapsed() < Duration::from_secs(30),
+            "sampler did not stop"
+        );
+    }
+    assert!(samples > 0);
+    // Процесс завершился, новых сэмплов больше нет
+    assert!(!sampler.is_running());
+    assert!(sampler.next().is_none());
+    child.wait().unwrap();
+
+    let rss = sampler.data_collector.series("rss").unwrap();
+    assert_eq!(rss.points(Downsample::Max).len(), samples);
+    assert!(sampler.data_collector.series("unknown").is_none());
+}